@@ -0,0 +1,44 @@
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct OAuthClient {
+    pub id: Uuid,
+    /// Hash of the client secret, which is [`None`] for public clients.
+    pub secret_hash: Option<String>,
+    pub secret_salt: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub organization_id: Option<Uuid>,
+    pub require_pkce: bool,
+    /// The scopes the client may request.
+    pub scopes: Vec<String>,
+    /// The grant types the client may use at the token endpoint.
+    pub grant_types: Vec<String>,
+    /// The response types the client may use at the authorization endpoint.
+    pub response_types: Vec<String>,
+}
+
+impl OAuthClient {
+    /// Whether the client is unable to keep a secret.
+    ///
+    /// Public clients always have to use PKCE.
+    pub fn is_public(&self) -> bool {
+        self.secret_hash.is_none()
+    }
+
+    /// Whether the client has to use PKCE for the authorization code flow.
+    pub fn requires_pkce(&self) -> bool {
+        self.require_pkce || self.is_public()
+    }
+
+    /// Whether the client may use the given grant type.
+    pub fn allows_grant(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|g| g == grant_type)
+    }
+
+    /// Whether the client may use the given response type.
+    pub fn allows_response_type(&self, response_type: &str) -> bool {
+        self.response_types.iter().any(|r| r == response_type)
+    }
+}
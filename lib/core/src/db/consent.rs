@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The scopes a user has agreed to share with a client.
+#[derive(Debug, Clone, FromRow)]
+pub struct Consent {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Consent {
+    /// Whether the user has agreed to share every one of `scopes`.
+    pub fn covers<'a>(&self, mut scopes: impl Iterator<Item = &'a str>) -> bool {
+        scopes.all(|s| self.scopes.iter().any(|c| c == s))
+    }
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailVerification {
+    pub id: Uuid,
+    /// Hex encoded SHA-256 hash of the token.
+    pub token_hash: String,
+    pub user_id: Uuid,
+    /// The email the token was sent to.
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+    /// When the token was used to verify the email.
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,3 +1,11 @@
+pub mod client;
+pub mod consent;
+pub mod email_verification;
+pub mod refresh_token;
 pub mod user;
 
+pub use client::*;
+pub use consent::*;
+pub use email_verification::*;
+pub use refresh_token::*;
 pub use user::*;
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    /// Hex encoded SHA-256 hash of the token.
+    pub token_hash: String,
+    /// Shared by every token rotated from the same grant.
+    pub family_id: Uuid,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub scope: Option<String>,
+    /// The ID of the access token issued together with this token.
+    pub access_token_jti: String,
+    pub access_token_expires_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// When the token was exchanged for a new one.
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -5,11 +5,21 @@ use uuid::Uuid;
 #[derive(Debug, Clone, FromRow)]
 pub struct User {
     pub id: Uuid,
+    #[sqlx(rename = "primary_email")]
     pub email: String,
     pub username: String,
     pub password_hash: String,
     pub password_salt: String,
     pub avatar: Option<String>,
+    /// When the primary email was verified, if ever.
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+impl User {
+    /// Whether the user has verified their primary email.
+    pub fn is_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+}
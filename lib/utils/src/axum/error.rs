@@ -7,7 +7,7 @@ use std::borrow::Cow;
 
 use axum::{
     Json,
-    http::{StatusCode, request::Parts},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, request::Parts},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
@@ -27,6 +27,12 @@ where
     /// HTTP status code generated by the server for this specific problem.
     #[serde(skip)]
     status: StatusCode,
+    /// Additional headers sent together with the response.
+    ///
+    /// Used for headers such as `WWW-Authenticate` or `Retry-After` that
+    /// clients rely on to handle the error.
+    #[serde(skip)]
+    headers: HeaderMap,
     /// A URI reference that identifies the problem type.
     ///
     /// This is dereferenced to human-readable documentation for the problem
@@ -97,6 +103,7 @@ where
     ) -> Self {
         Self {
             status,
+            headers: HeaderMap::new(),
             kind: Cow::from("about:blank"),
             title: title.into(),
             detail: detail.into(),
@@ -167,6 +174,14 @@ where
         self
     }
 
+    /// Add a header to the response of the [`HandlerError`].
+    ///
+    /// Replaces any previous value of the header.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
     /// Add a custom detail to the [`HandlerError`].
     pub fn with_extension(mut self, detail: D) -> Self {
         self.extension = Some(detail);
@@ -233,8 +248,11 @@ where
             }
         }
 
+        let headers = std::mem::take(&mut self.headers);
+
         (
             self.status,
+            headers,
             [("Content-Type", "application/problem+json")],
             Json(self),
         )
@@ -254,6 +272,7 @@ where
     fn from(value: E) -> Self {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            headers: HeaderMap::new(),
             kind: Cow::from("about:blank"),
             title: "Something went wrong".into(),
             detail: "If this issue persists, please contact an administrator.".into(),
@@ -307,6 +326,22 @@ mod test {
         assert!(response.status().is_client_error());
     }
 
+    #[test]
+    fn test_error_with_header() {
+        let response = HandlerError::<()>::unauthorized()
+            .with_header(
+                axum::http::header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Bearer"),
+            )
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[axum::http::header::WWW_AUTHENTICATE],
+            "Bearer"
+        );
+    }
+
     #[test]
     fn test_any_error_to_handler_result() {
         let example_handler = || -> HandlerResult<i32> { Ok("abc".parse::<i32>()?) };
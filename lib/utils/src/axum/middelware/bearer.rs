@@ -0,0 +1,370 @@
+//! Authentication of requests using bearer tokens.
+//!
+//! Tokens are read from the `Authorization` header, verified with the
+//! [`jwt`](crate::jwt) module and checked against a revocation denylist.
+//! Rejections carry the `WWW-Authenticate` header described by RFC 6750.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc6750
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::{FromRef, FromRequestParts, Request},
+    http::{HeaderMap, HeaderValue, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+use tower::{Layer, Service};
+
+use crate::{
+    axum::error::{HandlerError, HandlerResult},
+    jwt::{Claims, Extra, KeySet, Verifier},
+};
+
+/// A boxed future, used to keep [`Denylist`] object safe.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A list of tokens that have been revoked before they expired.
+pub trait Denylist: Send + Sync {
+    /// Whether the token with the given `jti` has been revoked.
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, anyhow::Result<bool>>;
+}
+
+/// Claims that carry the scopes granted to a token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc9068#section-2.2.3
+pub trait Scoped {
+    /// Space-delimited list of scopes granted to the token.
+    fn scope(&self) -> Option<&str>;
+
+    /// Whether `scope` was granted to the token.
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scope()
+            .is_some_and(|granted| granted.split_ascii_whitespace().any(|s| s == scope))
+    }
+}
+
+impl Scoped for Extra {
+    fn scope(&self) -> Option<&str> {
+        self.get("scope")?.as_str()
+    }
+}
+
+/// How bearer tokens are authenticated.
+///
+/// The extractor and layer in this module get this from the state of the
+/// router, so it has to implement [`FromRef`] for the state.
+#[derive(Clone)]
+pub struct BearerAuth {
+    keys: Arc<KeySet>,
+    verifier: Arc<Verifier>,
+    denylist: Option<Arc<dyn Denylist>>,
+}
+
+impl BearerAuth {
+    /// Accepts tokens signed by `keys` that pass `verifier`.
+    pub fn new(keys: KeySet, verifier: Verifier) -> Self {
+        Self {
+            keys: Arc::new(keys),
+            verifier: Arc::new(verifier),
+            denylist: None,
+        }
+    }
+
+    /// Rejects tokens that have been added to `denylist`.
+    ///
+    /// Tokens without a `jti` are rejected, since they can't be revoked.
+    pub fn with_denylist(mut self, denylist: impl Denylist + 'static) -> Self {
+        self.denylist = Some(Arc::new(denylist));
+        self
+    }
+
+    /// Authenticates the bearer token in `headers`.
+    pub async fn authenticate<E>(&self, headers: &HeaderMap) -> HandlerResult<Claims<E>>
+    where
+        E: DeserializeOwned,
+    {
+        let token = bearer_token(headers).ok_or_else(|| {
+            HandlerError::unauthorized()
+                .with_header(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))
+        })?;
+
+        let claims = self
+            .verifier
+            .verify::<Claims<E>>(&self.keys, token)
+            .map_err(|err| invalid_token(&err))?
+            .claims;
+
+        if let Some(denylist) = self.denylist.as_deref() {
+            let jti = claims
+                .jti
+                .as_deref()
+                .ok_or_else(|| invalid_token("The token can't be revoked."))?;
+            if denylist.is_revoked(jti).await? {
+                return Err(invalid_token("The token has been revoked."));
+            }
+        }
+
+        Ok(claims)
+    }
+}
+
+/// The claims of an authenticated bearer token.
+///
+/// If the route is guarded by [`RequireScope`] the claims it verified are
+/// reused instead of verifying the token again.
+#[derive(Debug, Clone)]
+pub struct Authenticated<E = Extra>(pub Claims<E>);
+
+impl<S, E> FromRequestParts<S> for Authenticated<E>
+where
+    S: Send + Sync,
+    BearerAuth: FromRef<S>,
+    E: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    type Rejection = HandlerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(authenticated) = parts.extensions.get::<Self>() {
+            return Ok(authenticated.clone());
+        }
+
+        let claims = BearerAuth::from_ref(state)
+            .authenticate(&parts.headers)
+            .await?;
+        Ok(Authenticated(claims))
+    }
+}
+
+/// A layer that rejects requests without a bearer token granting `scope`.
+///
+/// The verified claims are made available to handlers through
+/// [`Authenticated`].
+pub struct RequireScope<E = Extra> {
+    auth: BearerAuth,
+    scope: &'static str,
+    _claims: PhantomData<fn() -> E>,
+}
+
+impl<E> RequireScope<E> {
+    pub fn new(auth: BearerAuth, scope: &'static str) -> Self {
+        Self {
+            auth,
+            scope,
+            _claims: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for RequireScope<E> {
+    fn clone(&self) -> Self {
+        Self::new(self.auth.clone(), self.scope)
+    }
+}
+
+impl<S, E> Layer<S> for RequireScope<E> {
+    type Service = RequireScopeService<S, E>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireScopeService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service created by [`RequireScope`].
+pub struct RequireScopeService<S, E> {
+    inner: S,
+    layer: RequireScope<E>,
+}
+
+impl<S: Clone, E> Clone for RequireScopeService<S, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, E> Service<Request> for RequireScopeService<S, E>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+    E: DeserializeOwned + Scoped + Clone + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        // The clone might not be ready, so the ready service is used instead.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let RequireScope { auth, scope, .. } = self.layer.clone();
+
+        Box::pin(async move {
+            let claims = match auth.authenticate::<E>(req.headers()).await {
+                Ok(claims) => claims,
+                Err(err) => return Ok(err.into_response()),
+            };
+
+            if !claims.extra.has_scope(scope) {
+                return Ok(insufficient_scope(scope).into_response());
+            }
+
+            req.extensions_mut().insert(Authenticated(claims));
+            inner.call(req).await
+        })
+    }
+}
+
+/// Gets the token from an `Authorization: Bearer` header.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6750#section-2.1
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then_some(token.trim())
+}
+
+/// The token is expired, revoked, malformed or otherwise invalid.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6750#section-3.1
+fn invalid_token(description: impl ToString) -> HandlerError {
+    let description = description.to_string().replace('"', "'");
+    let challenge = format!(r#"Bearer error="invalid_token", error_description="{description}""#);
+
+    HandlerError::unauthorized().with_header(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::try_from(challenge)
+            .unwrap_or_else(|_| HeaderValue::from_static(r#"Bearer error="invalid_token""#)),
+    )
+}
+
+/// The token is valid, but was not granted `scope`.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6750#section-3.1
+fn insufficient_scope(scope: &str) -> HandlerError {
+    let challenge = format!(r#"Bearer error="insufficient_scope", scope="{scope}""#);
+
+    HandlerError::forbidden().with_header(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::try_from(challenge)
+            .unwrap_or_else(|_| HeaderValue::from_static(r#"Bearer error="insufficient_scope""#)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::jwt::Key;
+
+    use axum::{Router, body::Body, http::StatusCode, routing::get};
+    use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    struct RevokedJtis(Vec<&'static str>);
+
+    impl Denylist for RevokedJtis {
+        fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, anyhow::Result<bool>> {
+            Box::pin(async move { Ok(self.0.contains(&jti)) })
+        }
+    }
+
+    fn keys() -> KeySet {
+        let der = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", der.as_ref()));
+        KeySet::new(Key::from_pem("test", pem.as_bytes()).unwrap())
+    }
+
+    fn token(keys: &KeySet, jti: &str, scope: &str) -> String {
+        let claims = json!({
+            "sub": "user",
+            "exp": chrono::Utc::now().timestamp() + 60,
+            "jti": jti,
+            "scope": scope,
+        });
+        keys.encode(&claims).unwrap()
+    }
+
+    fn app(keys: KeySet) -> Router {
+        let auth =
+            BearerAuth::new(keys, Verifier::new()).with_denylist(RevokedJtis(vec!["revoked"]));
+
+        Router::new()
+            .route(
+                "/",
+                get(|Authenticated(claims): Authenticated| async move { claims.sub }),
+            )
+            .layer(RequireScope::<Extra>::new(auth.clone(), "read"))
+            .with_state(auth)
+    }
+
+    async fn send(app: Router, token: Option<&str>) -> Response {
+        let mut req = Request::get("/");
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    fn challenge(res: &Response) -> &str {
+        res.headers()[header::WWW_AUTHENTICATE].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_token() {
+        let res = send(app(keys()), None).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge(&res), "Bearer");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token() {
+        let res = send(app(keys()), Some("not-a-token")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(challenge(&res).starts_with(r#"Bearer error="invalid_token""#));
+
+        let keys = keys();
+        let revoked = token(&keys, "revoked", "read");
+        let res = send(app(keys), Some(&revoked)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_scope() {
+        let keys = keys();
+        let token = token(&keys, "id", "write");
+        let res = send(app(keys), Some(&token)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            challenge(&res),
+            r#"Bearer error="insufficient_scope", scope="read""#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticated() {
+        let keys = keys();
+        let token = token(&keys, "id", "read write");
+        let res = send(app(keys), Some(&token)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -1 +1,3 @@
+#[cfg(feature = "jwt")]
+pub mod bearer;
 pub mod validate;
@@ -6,7 +6,7 @@ use axum::{
     http::StatusCode,
 };
 use serde::{Serialize, de::DeserializeOwned};
-use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
 
 use crate::axum::error::{HandlerError, HandlerResult};
 
@@ -25,37 +25,84 @@ pub struct ValidationErrorResponse {
 }
 
 /// Errors in the individual fields.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct FieldErrors(pub Vec<Cow<'static, str>>);
 
-
 impl From<ValidationErrors> for ValidationErrorResponse {
     fn from(ValidationErrors(errors): ValidationErrors) -> Self {
-        let mut error_map = HashMap::new();
+        let mut error_map: HashMap<_, FieldErrors> = HashMap::new();
 
         for (field, kind) in errors {
-            error_map.insert(field.into(), kind.into());
+            match kind {
+                // Errors from schema validation are reported on the field they
+                // name in their `field` parameter, if any.
+                ValidationErrorsKind::Field(errors) if field == "__all__" => {
+                    for error in errors {
+                        let field = error
+                            .params
+                            .get("field")
+                            .and_then(|field| field.as_str())
+                            .map_or(field.clone(), |field| Cow::Owned(field.to_string()));
+                        error_map.entry(field).or_default().0.push(message(error));
+                    }
+                }
+                kind => {
+                    let errors = FieldErrors::from(kind);
+                    error_map.entry(field).or_default().0.extend(errors.0);
+                }
+            }
         }
 
-        Self { validation_errors: error_map }
+        Self {
+            validation_errors: error_map,
+        }
     }
 }
 
 impl From<ValidationErrorsKind> for FieldErrors {
     fn from(err: ValidationErrorsKind) -> Self {
-		dbg!(&err);
         match err {
-            ValidationErrorsKind::Field(errors) => FieldErrors(
+            ValidationErrorsKind::Field(errors) => {
+                FieldErrors(errors.into_iter().map(message).collect())
+            }
+            ValidationErrorsKind::Struct(errors) => FieldErrors(nested("", *errors)),
+            ValidationErrorsKind::List(errors) => FieldErrors(
                 errors
                     .into_iter()
-                    .map(|e| e.message.unwrap_or_else(|| "Unkown validation error".into()))
+                    .flat_map(|(index, errors)| nested(&format!("[{index}]."), *errors))
                     .collect(),
             ),
-            _ => todo!(),
         }
     }
 }
 
+/// The messages of errors in a nested struct, each prefixed with `prefix` and
+/// the field it belongs to, like `[0].name: Name is required`.
+fn nested(prefix: &str, errors: ValidationErrors) -> Vec<Cow<'static, str>> {
+    let mut fields: Vec<_> = ValidationErrorResponse::from(errors)
+        .validation_errors
+        .into_iter()
+        .collect();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    fields
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors
+                .0
+                .into_iter()
+                .map(move |message| Cow::Owned(format!("{prefix}{field}: {message}")))
+        })
+        .collect()
+}
+
+/// The message of a validation error.
+#[inline]
+fn message(err: ValidationError) -> Cow<'static, str> {
+    err.message
+        .unwrap_or_else(|| "Unkown validation error".into())
+}
+
 impl<S, T> FromRequest<S> for Validated<Json<T>>
 where
     S: Send + Sync,
@@ -106,3 +153,51 @@ fn unparseable<T: std::error::Error>(_: T) -> HandlerError<ValidationErrorRespon
         "Couldn't parse request body.",
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_errors_on_field() {
+        let mut errors = ValidationErrors::new();
+        let mut tagged = ValidationError::new("tagged").with_message("Tagged".into());
+        tagged.add_param("field".into(), &"password");
+        errors.add("__all__", tagged);
+        errors.add(
+            "__all__",
+            ValidationError::new("untagged").with_message("Untagged".into()),
+        );
+        errors.add(
+            "password",
+            ValidationError::new("length").with_message("Length".into()),
+        );
+
+        let res = ValidationErrorResponse::from(errors).validation_errors;
+        assert_eq!(res["password"].0.len(), 2);
+        assert_eq!(res["__all__"].0, ["Untagged"]);
+    }
+
+    #[test]
+    fn test_nested_errors() {
+        let mut inner = ValidationErrors::new();
+        inner.add(
+            "name",
+            ValidationError::new("required").with_message("Required".into()),
+        );
+
+        let mut errors = ValidationErrors::new();
+        errors.errors_mut().insert(
+            "owner".into(),
+            ValidationErrorsKind::Struct(Box::new(inner.clone())),
+        );
+        errors.errors_mut().insert(
+            "members".into(),
+            ValidationErrorsKind::List([(2, Box::new(inner))].into()),
+        );
+
+        let res = ValidationErrorResponse::from(errors).validation_errors;
+        assert_eq!(res["owner"].0, ["name: Required"]);
+        assert_eq!(res["members"].0, ["[2].name: Required"]);
+    }
+}
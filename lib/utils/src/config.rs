@@ -10,15 +10,16 @@
 /// Usage together with the [`crate::env`] module and a [`std::sync::LazyLock`].
 /// 
 /// ```
+/// # use std::sync::LazyLock;
+/// # use lerpz_utils::{env::{get_env, get_env_parse}, generate_config};
 /// generate_config! {
-/// 	ENV: String = get_env,
-/// 	SOME_INTEGER: u32 = get_env_parse,
+///     ENV: String = get_env,
+///     SOME_INTEGER: u32 = get_env_parse
 /// }
-/// 
-/// 
-///	pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
-/// 	Config::from_env().unwrap()
-/// }) 
+///
+/// pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
+///     Config::from_env().unwrap()
+/// });
 /// ```
 #[macro_export]
 macro_rules! generate_config {
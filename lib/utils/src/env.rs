@@ -40,3 +40,21 @@ where
         )
     })
 }
+
+/// Get an environment variable that may be unset and try to parse it into the
+/// generic type `T`.
+///
+/// Returns [`None`] if the variable is unset or empty, and an error if the
+/// parsing fails.
+pub fn get_env_opt<K, T>(key: K) -> Result<Option<T>>
+where
+    K: AsRef<OsStr> + Copy,
+    T: FromStr,
+{
+    match get_env(key) {
+        Ok(variable) if variable.is_empty() => Ok(None),
+        Ok(_) => get_env_parse(key).map(Some),
+        Err(Error::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -1,21 +1,144 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// Claims that are not registered by RFC 7519.
+pub type Extra = Map<String, Value>;
 
 /// Represent all claims for a token.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Claims {
+///
+/// Holds the registered claims, while any other claim ends up in `extra`.
+/// Services with claims of their own can use a struct for `extra` instead of
+/// the default map.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7519#section-4.1
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Claims<E = Extra> {
     /// What audience the token is for.
-    #[serde(skip_serializing_if = "String::is_empty")]
-    pub aud: String,
+    #[serde(default, skip_serializing_if = "Audience::is_empty")]
+    pub aud: Audience,
     /// Who issued the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub iss: String,
     /// Subject of the token.
-    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub sub: String,
     /// Which time the token will expire.
     pub exp: i64,
     /// When the token will be valid.
+    #[serde(default)]
     pub nbf: i64,
     /// When the token was issued.
+    #[serde(default)]
     pub iat: i64,
+    /// Unique identifier of the token.
+    ///
+    /// Used to revoke a single token before it expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Claims that are not registered.
+    #[serde(flatten)]
+    pub extra: E,
+}
+
+/// The recipients a token is intended for.
+///
+/// Serialized as a single string when there is only one audience and as an
+/// array otherwise, as both forms are allowed.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.3
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Audience(Vec<String>);
+
+impl Audience {
+    /// Whether the token has no audience.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether `aud` is one of the audiences.
+    pub fn contains(&self, aud: &str) -> bool {
+        self.0.iter().any(|a| a == aud)
+    }
+
+    /// Returns an iterator over the audiences.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl From<String> for Audience {
+    fn from(aud: String) -> Self {
+        Self(vec![aud])
+    }
+}
+
+impl From<&str> for Audience {
+    fn from(aud: &str) -> Self {
+        Self(vec![aud.into()])
+    }
+}
+
+impl From<Vec<String>> for Audience {
+    fn from(aud: Vec<String>) -> Self {
+        Self(aud)
+    }
+}
+
+impl Serialize for Audience {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [aud] => aud.serialize(serializer),
+            auds => auds.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Audience {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(aud) => Self(vec![aud]),
+            OneOrMany::Many(auds) => Self(auds),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_audience() {
+        let single: Claims = serde_json::from_value(json!({ "aud": "a", "exp": 0 })).unwrap();
+        assert_eq!(single.aud, Audience::from("a"));
+        assert_eq!(serde_json::to_value(&single.aud).unwrap(), json!("a"));
+
+        let many: Claims = serde_json::from_value(json!({ "aud": ["a", "b"], "exp": 0 })).unwrap();
+        assert!(many.aud.contains("b"));
+        assert_eq!(serde_json::to_value(&many.aud).unwrap(), json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_extra_claims() {
+        let claims: Claims = serde_json::from_value(json!({
+            "sub": "user",
+            "exp": 0,
+            "org": "lerpz",
+        }))
+        .unwrap();
+        assert_eq!(claims.extra["org"], "lerpz");
+        assert!(!claims.extra.contains_key("sub"));
+
+        let value = serde_json::to_value(&claims).unwrap();
+        assert_eq!(value["org"], "lerpz");
+        assert!(value.get("aud").is_none());
+    }
 }
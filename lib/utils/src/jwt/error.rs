@@ -1,3 +1,5 @@
+use jsonwebtoken::errors::ErrorKind;
+
 /// A type alias for [`Result<T, Error>`].
 ///
 /// Used by this module to return the same error for each [`Result`].
@@ -8,5 +10,45 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
 	#[error("token error: {0}")]
-	TokenError(#[from] jsonwebtoken::errors::Error),
+	TokenError(jsonwebtoken::errors::Error),
+	#[error("token has expired")]
+	Expired,
+	#[error("token is not valid yet")]
+	NotYetValid,
+	#[error("token is not intended for this audience")]
+	InvalidAudience,
+	#[error("token was not issued by the expected issuer")]
+	InvalidIssuer,
+	#[error("token has an invalid signature")]
+	InvalidSignature,
+	#[error("token is signed with an algorithm that is not allowed")]
+	InvalidAlgorithm,
+	#[error("token is not of the expected type")]
+	InvalidType,
+	#[error("token is missing the \"{0}\" claim")]
+	MissingClaim(String),
+	#[error("token is malformed")]
+	Malformed,
+	#[error("invalid key: {0}")]
+	InvalidKey(&'static str),
+	#[error("token was not signed by a known key")]
+	UnknownKey,
+	#[error("no key available for signing tokens")]
+	MissingSigningKey,
+}
+
+impl From<jsonwebtoken::errors::Error> for Error {
+	fn from(err: jsonwebtoken::errors::Error) -> Self {
+		match err.kind() {
+			ErrorKind::ExpiredSignature => Self::Expired,
+			ErrorKind::ImmatureSignature => Self::NotYetValid,
+			ErrorKind::InvalidAudience => Self::InvalidAudience,
+			ErrorKind::InvalidIssuer => Self::InvalidIssuer,
+			ErrorKind::InvalidSignature => Self::InvalidSignature,
+			ErrorKind::InvalidAlgorithm => Self::InvalidAlgorithm,
+			ErrorKind::MissingRequiredClaim(claim) => Self::MissingClaim(claim.clone()),
+			ErrorKind::InvalidToken | ErrorKind::Base64(_) | ErrorKind::Utf8(_) => Self::Malformed,
+			_ => Self::TokenError(err),
+		}
+	}
 }
@@ -0,0 +1,368 @@
+//! Asymmetric keys used for signing and verifying tokens.
+//!
+//! Tokens are signed with the current key of a [`KeySet`] and carry its `kid`
+//! in the header. Previous keys are kept around for verification only, so
+//! tokens issued before a rotation stay valid until they expire.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc7517
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, decode_header,
+    encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
+use ring::{
+    rand::SystemRandom,
+    rsa::PublicKeyComponents,
+    signature::{
+        ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING, EcdsaKeyPair,
+        Ed25519KeyPair, KeyPair, RsaKeyPair,
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{Error, Result};
+
+/// A key used for verifying tokens, and for signing them if the private key
+/// is known.
+#[derive(Clone)]
+pub struct Key {
+    kid: String,
+    alg: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    jwk: Jwk,
+}
+
+impl Key {
+    /// Loads a private key from a PEM file.
+    ///
+    /// Supports PKCS#8 encoded RSA, P-256, P-384 and Ed25519 keys, as well as
+    /// PKCS#1 encoded RSA keys. The algorithm is picked based on the type of
+    /// the key: `RS256`, `ES256`, `ES384` or `EdDSA`.
+    pub fn from_pem(kid: impl Into<String>, pem: &[u8]) -> Result<Self> {
+        let parsed = pem::parse(pem).map_err(|_| Error::InvalidKey("not a valid PEM file"))?;
+        let der = parsed.contents();
+        let rng = SystemRandom::new();
+
+        let (alg, params, encoding) = if parsed.tag() == "RSA PRIVATE KEY" {
+            let pair =
+                RsaKeyPair::from_der(der).map_err(|_| Error::InvalidKey("not a valid RSA key"))?;
+            (
+                Algorithm::RS256,
+                rsa_params(&pair),
+                EncodingKey::from_rsa_pem(pem)?,
+            )
+        } else if parsed.tag() != "PRIVATE KEY" {
+            return Err(Error::InvalidKey("not a private key"));
+        } else if let Ok(pair) = RsaKeyPair::from_pkcs8(der) {
+            (
+                Algorithm::RS256,
+                rsa_params(&pair),
+                EncodingKey::from_rsa_pem(pem)?,
+            )
+        } else if let Ok(pair) =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &rng)
+        {
+            let params = ec_params(EllipticCurve::P256, pair.public_key().as_ref());
+            (Algorithm::ES256, params, EncodingKey::from_ec_pem(pem)?)
+        } else if let Ok(pair) =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, der, &rng)
+        {
+            let params = ec_params(EllipticCurve::P384, pair.public_key().as_ref());
+            (Algorithm::ES384, params, EncodingKey::from_ec_pem(pem)?)
+        } else if let Ok(pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+            let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: BASE64_URL_SAFE_NO_PAD.encode(pair.public_key()),
+            });
+            (Algorithm::EdDSA, params, EncodingKey::from_ed_pem(pem)?)
+        } else {
+            return Err(Error::InvalidKey("unsupported key type"));
+        };
+
+        let jwk = Jwk {
+            common: common_params(kid.into(), alg)?,
+            algorithm: params,
+        };
+
+        Ok(Self {
+            encoding: Some(encoding),
+            ..Self::from_jwk(&jwk)?
+        })
+    }
+
+    /// Creates a key that can only verify tokens from a public JWK.
+    ///
+    /// The `kid` parameter is required, while the algorithm is derived from
+    /// the type of the key if `alg` is missing.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let kid = jwk
+            .common
+            .key_id
+            .clone()
+            .ok_or(Error::InvalidKey("the key has no \"kid\""))?;
+
+        let alg = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+            (Some(alg), _) => alg.to_string().parse()?,
+            (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+            (None, AlgorithmParameters::EllipticCurve(p)) if p.curve == EllipticCurve::P384 => {
+                Algorithm::ES384
+            }
+            (None, AlgorithmParameters::EllipticCurve(_)) => Algorithm::ES256,
+            (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+            (None, AlgorithmParameters::OctetKey(_)) => {
+                return Err(Error::InvalidKey("symmetric keys can't be published"));
+            }
+        };
+
+        Ok(Self {
+            kid,
+            alg,
+            encoding: None,
+            decoding: DecodingKey::from_jwk(jwk)?,
+            jwk: jwk.clone(),
+        })
+    }
+
+    /// The identifier of this key, used as the `kid` header of tokens.
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// The algorithm used when signing with this key.
+    pub fn alg(&self) -> Algorithm {
+        self.alg
+    }
+
+    /// The public part of this key.
+    pub fn jwk(&self) -> &Jwk {
+        &self.jwk
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key")
+            .field("kid", &self.kid)
+            .field("alg", &self.alg)
+            .field("private", &self.encoding.is_some())
+            .finish()
+    }
+}
+
+/// The keys of an issuer.
+///
+/// The first key is the current one and is used for signing. Every key can
+/// be used for verifying tokens.
+#[derive(Debug, Clone)]
+pub struct KeySet {
+    keys: Vec<Key>,
+}
+
+impl KeySet {
+    /// Creates a key set that signs tokens with `current`.
+    pub fn new(current: Key) -> Self {
+        Self {
+            keys: vec![current],
+        }
+    }
+
+    /// Adds a key that was used before the current one.
+    ///
+    /// Tokens signed with it can still be verified, but no new tokens will be
+    /// signed with it.
+    pub fn with_previous(mut self, key: Key) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Creates a key set that can only verify tokens, from the keys published
+    /// by an issuer.
+    ///
+    /// The set has to contain at least one key.
+    pub fn from_jwks(jwks: &JwkSet) -> Result<Self> {
+        if jwks.keys.is_empty() {
+            return Err(Error::InvalidKey("the key set is empty"));
+        }
+
+        let keys = jwks
+            .keys
+            .iter()
+            .map(Key::from_jwk)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { keys })
+    }
+
+    /// The key new tokens are signed with.
+    pub fn current(&self) -> &Key {
+        &self.keys[0]
+    }
+
+    /// Finds a key by its `kid`.
+    pub fn find(&self, kid: &str) -> Option<&Key> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    /// The public keys of this set, as served from `jwks.json`.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+
+    /// Signs the claims with the current key.
+    pub fn encode(&self, claims: &impl Serialize) -> Result<String> {
+        self.encode_typed("JWT", claims)
+    }
+
+    /// Signs the claims with the current key, setting the `typ` header.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7519#section-5.1
+    pub fn encode_typed(&self, typ: &str, claims: &impl Serialize) -> Result<String> {
+        let key = self.current();
+        let encoding = key.encoding.as_ref().ok_or(Error::MissingSigningKey)?;
+
+        let mut header = Header::new(key.alg);
+        header.typ = Some(typ.into());
+        header.kid = Some(key.kid.clone());
+
+        Ok(encode(&header, claims, encoding)?)
+    }
+
+    /// Verifies a token with the key named by its `kid` header.
+    ///
+    /// Only the algorithm of that key is accepted, regardless of what
+    /// `validation` allows.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> Result<TokenData<T>> {
+        let header = decode_header(token)?;
+        let key = header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.find(kid))
+            .ok_or(Error::UnknownKey)?;
+
+        let mut validation = validation.clone();
+        validation.algorithms = vec![key.alg];
+
+        Ok(decode(token, &key.decoding, &validation)?)
+    }
+}
+
+fn common_params(kid: String, alg: Algorithm) -> Result<CommonParameters> {
+    Ok(CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: Some(format!("{alg:?}").parse::<KeyAlgorithm>()?),
+        key_id: Some(kid),
+        ..Default::default()
+    })
+}
+
+fn rsa_params(pair: &RsaKeyPair) -> AlgorithmParameters {
+    let public = PublicKeyComponents::<Vec<u8>>::from(pair.public());
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: BASE64_URL_SAFE_NO_PAD.encode(public.n),
+        e: BASE64_URL_SAFE_NO_PAD.encode(public.e),
+    })
+}
+
+/// Splits an uncompressed elliptic curve point into its coordinates.
+fn ec_params(curve: EllipticCurve, point: &[u8]) -> AlgorithmParameters {
+    let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+    AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+        key_type: EllipticCurveKeyType::EC,
+        curve,
+        x: BASE64_URL_SAFE_NO_PAD.encode(x),
+        y: BASE64_URL_SAFE_NO_PAD.encode(y),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::jwt::Claims;
+
+    fn ed25519_key(kid: &str) -> Key {
+        let der = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", der.as_ref()));
+        Key::from_pem(kid, pem.as_bytes()).unwrap()
+    }
+
+    fn es256_key(kid: &str) -> Key {
+        let der =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", der.as_ref()));
+        Key::from_pem(kid, pem.as_bytes()).unwrap()
+    }
+
+    fn claims() -> Claims {
+        Claims {
+            aud: "client".into(),
+            iss: "issuer".into(),
+            sub: "user".into(),
+            exp: chrono::Utc::now().timestamp() + 60,
+            ..Default::default()
+        }
+    }
+
+    fn validation() -> Validation {
+        let mut validation = Validation::default();
+        validation.set_audience(&["client"]);
+        validation
+    }
+
+    #[test]
+    fn test_key_algorithm() {
+        assert_eq!(ed25519_key("a").alg(), Algorithm::EdDSA);
+        assert_eq!(es256_key("b").alg(), Algorithm::ES256);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = KeySet::new(ed25519_key("old"));
+        let token = old.encode(&claims()).unwrap();
+
+        let keys = KeySet::new(es256_key("new")).with_previous(old.current().clone());
+        let data = keys.decode::<Claims>(&token, &validation()).unwrap();
+        assert_eq!(data.header.kid.as_deref(), Some("old"));
+        assert_eq!(data.claims.sub, "user");
+
+        let token = keys.encode(&claims()).unwrap();
+        assert!(matches!(
+            old.decode::<Claims>(&token, &validation()),
+            Err(Error::UnknownKey)
+        ));
+    }
+
+    #[test]
+    fn test_verify_with_jwks() {
+        let keys = KeySet::new(es256_key("current"));
+        let token = keys.encode(&claims()).unwrap();
+
+        let json = serde_json::to_string(&keys.jwks()).unwrap();
+        let public = KeySet::from_jwks(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert!(public.decode::<Claims>(&token, &validation()).is_ok());
+        assert!(matches!(
+            public.encode(&claims()),
+            Err(Error::MissingSigningKey)
+        ));
+
+        assert!(matches!(
+            KeySet::from_jwks(&JwkSet { keys: vec![] }),
+            Err(Error::InvalidKey(_))
+        ));
+    }
+}
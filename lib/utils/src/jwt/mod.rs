@@ -4,21 +4,39 @@
 pub mod claims;
 /// Errors that can occur when working with JWT tokens.
 pub mod error;
+/// Keys used for signing and verifying JWT tokens.
+pub mod keys;
+/// Policies for verifying JWT tokens.
+pub mod verifier;
 
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, TokenData, Validation, decode, encode};
+use jsonwebtoken::{Header, decode, encode};
+use serde::{Serialize, de::DeserializeOwned};
 
-pub use claims::Claims;
+pub use claims::{Audience, Claims, Extra};
 pub use error::{Error, Result};
+pub use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, TokenData, Validation,
+    jwk::{Jwk, JwkSet},
+};
+pub use keys::{Key, KeySet};
+pub use verifier::Verifier;
 
-pub fn encode_jwt(claims: impl Into<Claims>, key: &EncodingKey) -> Result<String> {
+pub fn encode_jwt<T: Serialize>(claims: &T, key: &EncodingKey) -> Result<String> {
     let header = Header::default();
-    let claims = claims.into();
-    let token = encode(&header, &claims, key).map_err(Error::TokenError)?;
+    let token = encode(&header, claims, key)?;
     Ok(token)
 }
 
-pub fn decode_jwt(token: &str, key: &DecodingKey) -> Result<TokenData<Claims>> {
-    let validation = Validation::default();
-    let claims = decode::<Claims>(token, key, &validation).map_err(Error::TokenError)?;
+pub fn decode_jwt<T: DeserializeOwned>(token: &str, key: &DecodingKey) -> Result<TokenData<T>> {
+    decode_jwt_with(token, key, &Validation::default())
+}
+
+/// Decodes a token using a custom [`Validation`].
+pub fn decode_jwt_with<T: DeserializeOwned>(
+    token: &str,
+    key: &DecodingKey,
+    validation: &Validation,
+) -> Result<TokenData<T>> {
+    let claims = decode::<T>(token, key, validation)?;
     Ok(claims)
 }
@@ -0,0 +1,284 @@
+//! A reusable policy for verifying tokens.
+
+use jsonwebtoken::{Algorithm, TokenData, Validation, decode_header};
+use serde::de::DeserializeOwned;
+
+use super::{Error, KeySet, Result};
+
+/// The algorithms accepted by a [`Verifier`] unless configured otherwise.
+///
+/// Only asymmetric algorithms are accepted, so a public key can never be
+/// used as a shared secret.
+const DEFAULT_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// Verifies tokens against the keys of an issuer.
+///
+/// By default `exp` is required, tokens with an audience are rejected and no
+/// leeway is given. Tokens with an `nbf` in the future are always rejected,
+/// while tokens without one are accepted unless `nbf` is required.
+///
+/// # Example
+///
+/// ```
+/// # use lerpz_utils::jwt::verifier::Verifier;
+/// let verifier = Verifier::new()
+///     .with_issuer("https://auth.lerpz.local")
+///     .with_audience(&["relay"])
+///     .with_leeway(30)
+///     .with_required_claims(&["exp", "nbf", "sub"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    validation: Validation,
+    typ: Option<String>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.algorithms = DEFAULT_ALGORITHMS.to_vec();
+        validation.leeway = 0;
+        validation.validate_nbf = true;
+        Self {
+            validation,
+            typ: None,
+        }
+    }
+
+    /// Only accepts tokens issued by `iss`.
+    pub fn with_issuer(mut self, iss: &str) -> Self {
+        self.validation.set_issuer(&[iss]);
+        self
+    }
+
+    /// Only accepts tokens intended for one of `aud`.
+    pub fn with_audience(mut self, aud: &[&str]) -> Self {
+        self.validation.set_audience(aud);
+        self.validation.validate_aud = true;
+        self
+    }
+
+    /// Accepts tokens regardless of their audience.
+    pub fn with_any_audience(mut self) -> Self {
+        self.validation.aud = None;
+        self.validation.validate_aud = false;
+        self
+    }
+
+    /// Only accepts tokens with `typ` as their `typ` header.
+    ///
+    /// The `application/` prefix is optional and the comparison ignores case,
+    /// like for any media type.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.9
+    pub fn with_type(mut self, typ: &str) -> Self {
+        self.typ = Some(typ.into());
+        self
+    }
+
+    /// Only accepts tokens signed with one of `algorithms`.
+    pub fn with_algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        self.validation.algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Allows `exp` and `nbf` to be off by `seconds` to account for clock skew.
+    pub fn with_leeway(mut self, seconds: u64) -> Self {
+        self.validation.leeway = seconds;
+        self
+    }
+
+    /// Rejects tokens missing one of `claims`.
+    ///
+    /// Only `exp`, `nbf`, `aud`, `iss` and `sub` can be required.
+    pub fn with_required_claims(mut self, claims: &[&str]) -> Self {
+        self.validation.set_required_spec_claims(claims);
+        self
+    }
+
+    /// Verifies a token signed by one of `keys`.
+    pub fn verify<T: DeserializeOwned>(&self, keys: &KeySet, token: &str) -> Result<TokenData<T>> {
+        let header = decode_header(token)?;
+        if !self.validation.algorithms.contains(&header.alg) {
+            return Err(Error::InvalidAlgorithm);
+        }
+        let typ = header.typ.as_deref().unwrap_or_default();
+        if let Some(expected) = &self.typ
+            && !media_type_eq(typ, expected)
+        {
+            return Err(Error::InvalidType);
+        }
+
+        keys.decode(token, &self.validation)
+    }
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compares two media types, treating the `application/` prefix as optional.
+fn media_type_eq(a: &str, b: &str) -> bool {
+    fn strip(typ: &str) -> &str {
+        typ.get(..12)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("application/"))
+            .map_or(typ, |_| &typ[12..])
+    }
+    strip(a).eq_ignore_ascii_case(strip(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::jwt::{Claims, Key};
+
+    use chrono::Utc;
+    use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+
+    fn keys() -> KeySet {
+        let der = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", der.as_ref()));
+        KeySet::new(Key::from_pem("test", pem.as_bytes()).unwrap())
+    }
+
+    fn claims() -> Claims {
+        let now = Utc::now().timestamp();
+        Claims {
+            aud: "relay".into(),
+            iss: "issuer".into(),
+            sub: "user".into(),
+            exp: now + 60,
+            nbf: now,
+            iat: now,
+            ..Default::default()
+        }
+    }
+
+    fn verifier() -> Verifier {
+        Verifier::new()
+            .with_issuer("issuer")
+            .with_audience(&["relay"])
+    }
+
+    #[test]
+    fn test_verify() {
+        let keys = keys();
+        let token = keys.encode(&claims()).unwrap();
+        let data = verifier().verify::<Claims>(&keys, &token).unwrap();
+        assert_eq!(data.claims.sub, "user");
+    }
+
+    #[test]
+    fn test_expired() {
+        let keys = keys();
+        let token = keys
+            .encode(&Claims {
+                exp: Utc::now().timestamp() - 10,
+                ..claims()
+            })
+            .unwrap();
+
+        let res = verifier().verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::Expired)));
+        let res = verifier().with_leeway(30).verify::<Claims>(&keys, &token);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_not_yet_valid() {
+        let keys = keys();
+        let token = keys
+            .encode(&Claims {
+                nbf: Utc::now().timestamp() + 30,
+                ..claims()
+            })
+            .unwrap();
+
+        let res = verifier().verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::NotYetValid)));
+        let res = verifier().with_leeway(60).verify::<Claims>(&keys, &token);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_wrong_audience_and_issuer() {
+        let keys = keys();
+        let token = keys.encode(&claims()).unwrap();
+
+        let res = verifier()
+            .with_audience(&["other"])
+            .verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::InvalidAudience)));
+
+        let res = verifier()
+            .with_issuer("other")
+            .verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::InvalidIssuer)));
+    }
+
+    #[test]
+    fn test_bad_signature() {
+        let keys = keys();
+        let token = keys.encode(&claims()).unwrap();
+        let (payload, _) = token.rsplit_once('.').unwrap();
+        let forged = format!("{payload}.{}", "A".repeat(86));
+
+        let res = verifier().verify::<Claims>(&keys, &forged);
+        assert!(matches!(res, Err(Error::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_algorithm_not_allowed() {
+        let keys = keys();
+        let token = keys.encode(&claims()).unwrap();
+
+        let res = verifier()
+            .with_algorithms(&[Algorithm::ES256])
+            .verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::InvalidAlgorithm)));
+    }
+
+    #[test]
+    fn test_type() {
+        let keys = keys();
+        let verifier = verifier().with_type("at+jwt");
+
+        let token = keys.encode_typed("at+jwt", &claims()).unwrap();
+        assert!(verifier.verify::<Claims>(&keys, &token).is_ok());
+        let token = keys.encode_typed("application/AT+JWT", &claims()).unwrap();
+        assert!(verifier.verify::<Claims>(&keys, &token).is_ok());
+
+        let token = keys.encode(&claims()).unwrap();
+        let res = verifier.verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::InvalidType)));
+    }
+
+    #[test]
+    fn test_missing_claim() {
+        let keys = keys();
+        let token = keys
+            .encode(&Claims {
+                sub: String::new(),
+                ..claims()
+            })
+            .unwrap();
+
+        let res = verifier()
+            .with_required_claims(&["exp", "sub"])
+            .verify::<Claims>(&keys, &token);
+        assert!(matches!(res, Err(Error::MissingClaim(claim)) if claim == "sub"));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod config;
 pub mod env;
+pub mod html;
 
 #[cfg(feature = "pwd")]
 pub mod pwd;
@@ -7,5 +8,8 @@ pub mod pwd;
 #[cfg(feature = "jwt")]
 pub mod jwt;
 
+#[cfg(feature = "mail")]
+pub mod mail;
+
 #[cfg(feature = "axum")]
 pub mod axum;
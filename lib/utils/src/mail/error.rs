@@ -0,0 +1,28 @@
+/// A type alias for [`Result<T, Error>`].
+///
+/// Used by this module to return the same error for each [`Result`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// All the different errors the `mail` module might produce.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("invalid address: {0}")]
+	Address(#[from] lettre::address::AddressError),
+	#[error("couldn't build message: {0}")]
+	Message(#[from] lettre::error::Error),
+	#[error("smtp error: {0}")]
+	Smtp(#[from] lettre::transport::smtp::Error),
+	#[error("io error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("redis error: {0}")]
+	Redis(#[from] redis::RedisError),
+	#[error("couldn't (de)serialize queued mail: {0}")]
+	Serde(#[from] serde_json::Error),
+	#[error("unsupported mail transport url: {0}")]
+	UnsupportedUrl(String),
+	#[error("unknown template \"{0}\"")]
+	UnknownTemplate(String),
+	#[error("template is missing the \"{0}\" variable")]
+	MissingVariable(String),
+}
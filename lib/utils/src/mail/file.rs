@@ -0,0 +1,78 @@
+//! Dropping of mail into a local maildir.
+
+use std::path::PathBuf;
+
+use lettre::message::Mailbox;
+use uuid::Uuid;
+
+use super::{BoxFuture, Mail, Mailer, Result};
+
+/// Writes every mail into the `new` folder of a maildir.
+///
+/// Mail is written to the `tmp` folder first and then moved, so readers never
+/// see a partially written file.
+///
+/// Source: https://cr.yp.to/proto/maildir.html
+#[derive(Debug, Clone)]
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: Mailbox) -> Self {
+        Self {
+            dir: dir.into(),
+            from,
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = mail.to_message(&self.from)?;
+            let name = format!("{}.eml", Uuid::new_v4());
+
+            let tmp = self.dir.join("tmp");
+            let new = self.dir.join("new");
+            tokio::fs::create_dir_all(&tmp).await?;
+            tokio::fs::create_dir_all(&new).await?;
+
+            tokio::fs::write(tmp.join(&name), message.formatted()).await?;
+            tokio::fs::rename(tmp.join(&name), new.join(&name)).await?;
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_mailer() {
+        let dir = std::env::temp_dir().join(format!("lerpz-mail-{}", Uuid::new_v4()));
+        let mailer = FileMailer::new(&dir, "Lerpz <noreply@lerpz.com>".parse().unwrap());
+
+        let mail = Mail {
+            to: "user@example.com".into(),
+            subject: "Hello".into(),
+            text: "Hello, world!".into(),
+            html: Some("<p>Hello, world!</p>".into()),
+        };
+        mailer.send(&mail).await.unwrap();
+
+        let mut entries = std::fs::read_dir(dir.join("new")).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        let content = std::fs::read_to_string(path).unwrap();
+
+        assert!(content.contains("To: user@example.com"));
+        assert!(content.contains("Subject: Hello"));
+        assert!(content.contains("multipart/alternative"));
+        assert!(entries.next().is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
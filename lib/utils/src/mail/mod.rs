@@ -0,0 +1,89 @@
+//! Sending of emails.
+//!
+//! Everything that sends mail depends on the [`Mailer`] trait, so that the
+//! transport can be chosen at runtime:
+//!
+//! - [`SmtpMailer`] sends mail through an SMTP server.
+//! - [`FileMailer`] drops mail into a maildir, for development and tests.
+//! - [`MailQueue`] pushes mail onto a queue in Redis, which a [`MailWorker`]
+//!   sends with one of the other transports in the background.
+//!
+//! Messages are usually rendered from localized [`Templates`].
+
+pub mod file;
+pub mod queue;
+pub mod smtp;
+pub mod template;
+
+mod error;
+
+pub use error::{Error, Result};
+pub use file::FileMailer;
+pub use queue::{MailQueue, MailWorker};
+pub use smtp::SmtpMailer;
+pub use template::{Template, Templates};
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use lettre::message::{Mailbox, Message, MultiPart, SinglePart};
+use serde::{Deserialize, Serialize};
+
+/// A boxed future, used for the async methods of [`Mailer`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An email with a plain text body, and optionally an HTML body.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+impl Mail {
+    /// Builds the MIME message sent by the transports.
+    fn to_message(&self, from: &Mailbox) -> Result<Message> {
+        let builder = Message::builder()
+            .from(from.clone())
+            .to(self.to.parse()?)
+            .subject(&self.subject);
+
+        let message = match &self.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                self.text.clone(),
+                html.clone(),
+            ))?,
+            None => builder.singlepart(SinglePart::plain(self.text.clone()))?,
+        };
+        Ok(message)
+    }
+}
+
+/// Sends emails.
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<()>>;
+}
+
+impl<M: Mailer + ?Sized> Mailer for Arc<M> {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<()>> {
+        (**self).send(mail)
+    }
+}
+
+/// Creates a transport from a URL.
+///
+/// `smtp://` and `smtps://` URLs create an [`SmtpMailer`], as described by
+/// [`lettre::AsyncSmtpTransport::from_url`]. `file://` URLs create a
+/// [`FileMailer`] for the directory in the path.
+pub fn transport(url: &str, from: &str) -> Result<Arc<dyn Mailer>> {
+    let from: Mailbox = from.parse()?;
+
+    if let Some(path) = url.strip_prefix("file://") {
+        return Ok(Arc::new(FileMailer::new(path, from)));
+    }
+    if url.starts_with("smtp://") || url.starts_with("smtps://") {
+        return Ok(Arc::new(SmtpMailer::from_url(url, from)?));
+    }
+
+    Err(Error::UnsupportedUrl(url.into()))
+}
@@ -0,0 +1,257 @@
+//! A queue of mail in Redis, sent in the background.
+//!
+//! [`MailQueue`] only pushes mail onto a list, so sending never waits for the
+//! mail server. A [`MailWorker`] moves mail off the list onto a processing
+//! list and sends it, and only removes it from there once it has been sent or
+//! scheduled for a retry, so mail isn't lost if the worker crashes. Failed
+//! mail is retried with an exponential backoff, and is moved to a dead letter
+//! list once it has failed too many times.
+
+use std::{sync::Arc, sync::LazyLock, time::Duration};
+
+use redis::{AsyncCommands, Direction, Script, aio::MultiplexedConnection};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{BoxFuture, Mail, Mailer, Result};
+
+/// Moves retries that are due from the retry set back onto the queue.
+///
+/// `KEYS[1]` is the retry set, `KEYS[2]` the queue and `ARGV[1]` the current
+/// time in seconds.
+static PROMOTE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+        for _, job in ipairs(due) do
+            redis.call('ZREM', KEYS[1], job)
+            redis.call('LPUSH', KEYS[2], job)
+        end
+        return #due
+        ",
+    )
+});
+
+/// Moves everything on the processing list back onto the queue.
+///
+/// `KEYS[1]` is the processing list and `KEYS[2]` the queue.
+static REQUEUE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local moved = 0
+        while redis.call('LMOVE', KEYS[1], KEYS[2], 'LEFT', 'RIGHT') do
+            moved = moved + 1
+        end
+        return moved
+        ",
+    )
+});
+
+/// A mail on the queue, together with how often sending it has failed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Job {
+    /// Keeps identical mail apart in the retry set.
+    id: Uuid,
+    mail: Mail,
+    attempts: u32,
+}
+
+/// Pushes mail onto a queue in Redis.
+#[derive(Clone)]
+pub struct MailQueue {
+    redis: Arc<redis::Client>,
+    key: String,
+}
+
+impl MailQueue {
+    /// Creates a queue stored under `key`.
+    ///
+    /// Mail being sent is stored under `{key}:processing`, retries under
+    /// `{key}:retry` and mail that failed too many times under `{key}:dead`.
+    pub fn new(redis: Arc<redis::Client>, key: impl Into<String>) -> Self {
+        Self {
+            redis,
+            key: key.into(),
+        }
+    }
+
+    fn processing_key(&self) -> String {
+        format!("{}:processing", self.key)
+    }
+
+    fn retry_key(&self) -> String {
+        format!("{}:retry", self.key)
+    }
+
+    fn dead_key(&self) -> String {
+        format!("{}:dead", self.key)
+    }
+}
+
+impl Mailer for MailQueue {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let job = serde_json::to_string(&Job {
+                id: Uuid::new_v4(),
+                mail: mail.clone(),
+                attempts: 0,
+            })?;
+
+            let mut conn = self.redis.get_multiplexed_async_connection().await?;
+            let _: () = conn.lpush(&self.key, job).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Sends the mail on a [`MailQueue`] with another transport.
+pub struct MailWorker<M> {
+    queue: MailQueue,
+    mailer: M,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+impl<M: Mailer> MailWorker<M> {
+    /// Creates a worker that tries to send each mail 5 times, waiting 30
+    /// seconds before the first retry and doubling the wait for every retry.
+    pub fn new(queue: MailQueue, mailer: M) -> Self {
+        Self {
+            queue,
+            mailer,
+            max_attempts: 5,
+            backoff: Duration::from_secs(30),
+        }
+    }
+
+    /// How many times to try sending a mail before giving up on it.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// How long to wait before the first retry.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sends mail from the queue until the task is cancelled.
+    ///
+    /// Mail left on the processing list by a worker that stopped while sending
+    /// it is put back on the queue first, so it may be sent twice. Only one
+    /// worker should run per queue, as a worker starting up would otherwise
+    /// requeue mail the others are still sending.
+    ///
+    /// Errors talking to Redis are logged, after which the worker waits a bit
+    /// before trying again.
+    pub async fn run(self) {
+        if let Err(err) = self.requeue().await {
+            tracing::error!(error = %err, "failed requeueing unfinished mail");
+        }
+
+        loop {
+            if let Err(err) = self.step().await {
+                tracing::error!(error = %err, "mail worker failed");
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    /// Puts mail left on the processing list back onto the queue.
+    async fn requeue(&self) -> Result<()> {
+        let mut conn = self.queue.redis.get_multiplexed_async_connection().await?;
+
+        let requeued: u64 = REQUEUE_SCRIPT
+            .key(self.queue.processing_key())
+            .key(&self.queue.key)
+            .invoke_async(&mut conn)
+            .await?;
+        if requeued > 0 {
+            tracing::warn!(requeued, "requeued unfinished mail");
+        }
+
+        Ok(())
+    }
+
+    /// Sends the next mail on the queue, waiting a second for one to arrive.
+    async fn step(&self) -> Result<()> {
+        let mut conn = self.queue.redis.get_multiplexed_async_connection().await?;
+
+        let _: u64 = PROMOTE_SCRIPT
+            .key(self.queue.retry_key())
+            .key(&self.queue.key)
+            .arg(now())
+            .invoke_async(&mut conn)
+            .await?;
+
+        let popped: Option<String> = conn
+            .blmove(
+                &self.queue.key,
+                self.queue.processing_key(),
+                Direction::Right,
+                Direction::Left,
+                1.0,
+            )
+            .await?;
+        let Some(raw) = popped else {
+            return Ok(());
+        };
+
+        let mut job: Job = match serde_json::from_str(&raw) {
+            Ok(job) => job,
+            Err(err) => {
+                tracing::error!(error = %err, "dropping malformed mail job");
+                let mut pipe = redis::pipe();
+                pipe.atomic().lpush(self.queue.dead_key(), &raw).ignore();
+                return self.finish(&mut conn, pipe, &raw).await;
+            }
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if let Err(err) = self.mailer.send(&job.mail).await {
+            job.attempts += 1;
+            if job.attempts >= self.max_attempts {
+                tracing::error!(error = %err, to = %job.mail.to, "giving up sending mail");
+                pipe.lpush(self.queue.dead_key(), serde_json::to_string(&job)?)
+                    .ignore();
+            } else {
+                let delay = self.backoff * 2u32.saturating_pow(job.attempts - 1);
+                tracing::warn!(error = %err, to = %job.mail.to, ?delay, "retrying mail later");
+                pipe.zadd(
+                    self.queue.retry_key(),
+                    serde_json::to_string(&job)?,
+                    now() + delay.as_secs(),
+                )
+                .ignore();
+            }
+        }
+
+        self.finish(&mut conn, pipe, &raw).await
+    }
+
+    /// Removes a job from the processing list, together with the commands in
+    /// `pipe` that move it elsewhere.
+    async fn finish(
+        &self,
+        conn: &mut MultiplexedConnection,
+        mut pipe: redis::Pipeline,
+        raw: &str,
+    ) -> Result<()> {
+        let _: () = pipe
+            .lrem(self.queue.processing_key(), 1, raw)
+            .ignore()
+            .query_async(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The current unix time in seconds.
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,32 @@
+//! Sending of mail through an SMTP server.
+
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor, message::Mailbox};
+
+use super::{BoxFuture, Mail, Mailer, Result};
+
+/// Sends mail through an SMTP server.
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer for the server in the URL.
+    ///
+    /// The URL has the form `smtp[s]://[user[:password]@]host[:port][?tls=...]`.
+    pub fn from_url(url: &str, from: Mailbox) -> Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)?.build();
+        Ok(Self { transport, from })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = mail.to_message(&self.from)?;
+            self.transport.send(message).await?;
+            Ok(())
+        })
+    }
+}
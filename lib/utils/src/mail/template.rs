@@ -0,0 +1,213 @@
+//! Localized templates for mail.
+//!
+//! Templates contain `{{name}}` placeholders, which are replaced by variables
+//! when rendering. Variables are escaped in the HTML body.
+
+use std::collections::HashMap;
+
+use crate::html::escape_html;
+
+use super::{Error, Mail, Result};
+
+/// The subject and bodies of a mail, in one language.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+impl Template {
+    pub fn new(subject: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            text: text.into(),
+            html: None,
+        }
+    }
+
+    pub fn with_html(mut self, html: impl Into<String>) -> Self {
+        self.html = Some(html.into());
+        self
+    }
+}
+
+/// A set of templates, each available in one or more locales.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    default_locale: String,
+    templates: HashMap<(String, String), Template>,
+}
+
+impl Templates {
+    /// Creates an empty set of templates.
+    ///
+    /// Every template should exist in the default locale, since it is used
+    /// when none of the preferred locales are available.
+    pub fn new(default_locale: impl Into<String>) -> Self {
+        Self {
+            default_locale: default_locale.into(),
+            templates: HashMap::new(),
+        }
+    }
+
+    /// Adds a template in the given locale.
+    pub fn with(mut self, name: &str, locale: &str, template: Template) -> Self {
+        self.templates
+            .insert((name.into(), locale.to_lowercase()), template);
+        self
+    }
+
+    /// Renders a template for the recipient.
+    ///
+    /// The first of the `preferred` locales the template exists in is used.
+    /// A locale like `da-DK` falls back to `da` before moving on to the next.
+    pub fn render(
+        &self,
+        name: &str,
+        preferred: &[impl AsRef<str>],
+        to: impl Into<String>,
+        vars: &[(&str, &str)],
+    ) -> Result<Mail> {
+        let template = self
+            .find(name, preferred)
+            .ok_or_else(|| Error::UnknownTemplate(name.into()))?;
+
+        Ok(Mail {
+            to: to.into(),
+            subject: render(&template.subject, vars, |v| v.into())?,
+            text: render(&template.text, vars, |v| v.into())?,
+            html: template
+                .html
+                .as_deref()
+                .map(|html| render(html, vars, escape_html))
+                .transpose()?,
+        })
+    }
+
+    fn find(&self, name: &str, preferred: &[impl AsRef<str>]) -> Option<&Template> {
+        let get = |locale: &str| self.templates.get(&(name.into(), locale.to_lowercase()));
+
+        preferred
+            .iter()
+            .map(AsRef::as_ref)
+            .find_map(|locale| {
+                get(locale).or_else(|| locale.split_once('-').and_then(|(lang, _)| get(lang)))
+            })
+            .or_else(|| get(&self.default_locale))
+    }
+}
+
+/// Parses an `Accept-Language` header into locales, most preferred first.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc9110#section-12.5.4
+pub fn accept_language(header: &str) -> Vec<String> {
+    let mut locales: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let locale = params.next()?.trim();
+            let quality = params
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse().ok())?;
+            (!locale.is_empty() && locale != "*" && quality > 0.0)
+                .then(|| (locale.to_string(), quality))
+        })
+        .collect();
+
+    // The sort is stable, so locales with the same quality keep their order.
+    locales.sort_by(|a, b| b.1.total_cmp(&a.1));
+    locales.into_iter().map(|(locale, _)| locale).collect()
+}
+
+/// Replaces the `{{name}}` placeholders in `template`.
+fn render(
+    template: &str,
+    vars: &[(&str, &str)],
+    escape: impl Fn(&str) -> String,
+) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        let value = vars
+            .iter()
+            .find_map(|(k, v)| (*k == name).then_some(*v))
+            .ok_or_else(|| Error::MissingVariable(name.into()))?;
+
+        out.push_str(&rest[..start]);
+        out.push_str(&escape(value));
+        rest = &rest[start + end + 2..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates() -> Templates {
+        Templates::new("en")
+            .with(
+                "hello",
+                "en",
+                Template::new("Hello {{name}}", "Hello, {{ name }}!")
+                    .with_html("<p>Hello, {{name}}!</p>"),
+            )
+            .with(
+                "hello",
+                "da",
+                Template::new("Hej {{name}}", "Hej, {{name}}!"),
+            )
+    }
+
+    #[test]
+    fn test_render() {
+        let mail = templates()
+            .render("hello", &["en"], "a@example.com", &[("name", "<Bob>")])
+            .unwrap();
+
+        assert_eq!(mail.subject, "Hello <Bob>");
+        assert_eq!(mail.text, "Hello, <Bob>!");
+        assert_eq!(mail.html.as_deref(), Some("<p>Hello, &lt;Bob&gt;!</p>"));
+
+        let err = templates()
+            .render("hello", &["en"], "a@example.com", &[])
+            .unwrap_err();
+        assert!(matches!(err, Error::MissingVariable(name) if name == "name"));
+    }
+
+    #[test]
+    fn test_render_locale() {
+        let templates = templates();
+        let render = |preferred: &[&str]| {
+            templates
+                .render("hello", preferred, "a@example.com", &[("name", "Bob")])
+                .unwrap()
+                .subject
+        };
+
+        assert_eq!(render(&["da"]), "Hej Bob");
+        assert_eq!(render(&["da-DK", "en"]), "Hej Bob");
+        assert_eq!(render(&["fr", "da"]), "Hej Bob");
+        assert_eq!(render(&["fr"]), "Hello Bob");
+        assert_eq!(render(&[]), "Hello Bob");
+    }
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(
+            accept_language("da, en-GB;q=0.8, en;q=0.7, *;q=0.5"),
+            ["da", "en-GB", "en"]
+        );
+        assert_eq!(accept_language("en;q=0.5, da"), ["da", "en"]);
+        assert_eq!(accept_language("en;q=0, da;q=bad"), Vec::<String>::new());
+        assert!(accept_language("").is_empty());
+    }
+}
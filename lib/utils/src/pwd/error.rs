@@ -12,8 +12,16 @@ pub enum Error {
     FailSpawnBlockForValidate,
     #[error("failed spawning thread for hashing")]
     FailSpawnBlockForHash,
+    #[error("too many passwords are being hashed")]
+    Overloaded,
     #[error("failed parsing password: {0}")]
     PwdParsingFailed(String),
     #[error("scheme error: {0}")]
     SchemeError(#[from] scheme::error::Error),
+    #[error("invalid password policy configuration: {0}")]
+    PolicyConfig(#[from] crate::env::Error),
+    #[error("failed reading breach corpus: {0}")]
+    BreachCorpus(#[from] std::io::Error),
+    #[error("\"{0}\" isn't 5 hex characters")]
+    InvalidHashPrefix(String),
 }
@@ -4,19 +4,54 @@
 mod error;
 /// Parts needed for hashing and validating passwords.
 mod parts;
+/// Rules new passwords have to follow.
+pub mod policy;
+/// Bounded concurrency for hashing and validating passwords.
+pub mod pool;
 /// Schemas for hashing and validating passwords.
-mod scheme;
+pub mod scheme;
 
 use std::str::FromStr;
 
+use pool::pool;
+
 pub use error::{Error, Result};
 pub use parts::{HashParts, PwdParts};
-pub use scheme::{Scheme, get_scheme};
+pub use scheme::{
+    BUILTIN_DEFAULT_SCHEME, Scheme, default_scheme, get_scheme, register_scheme,
+    scheme_02::{Scheme02, calibrate},
+    set_default_scheme,
+};
+
+/// The outcome of validating a password against a hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PwdValidation {
+    /// The password matches and the hash is up to date.
+    Valid,
+    /// The password matches, but the hash wasn't made with the
+    /// [default scheme](default_scheme) and its current parameters. The
+    /// password should be hashed again while it is at hand.
+    Outdated,
+    /// The password doesn't match.
+    Invalid,
+}
+
+impl PwdValidation {
+    /// Whether the password matches, regardless of the hash being outdated.
+    pub fn is_valid(self) -> bool {
+        self != Self::Invalid
+    }
 
-/// Default scheme used for hashing passwords.
-pub static DEFAULT_SCHEME: &str = "01";
+    /// Whether the password matches but should be hashed again.
+    pub fn needs_rehash(self) -> bool {
+        self == Self::Outdated
+    }
+}
 
 /// Hash a password using the latest scheme.
+///
+/// Returns [`Error::Overloaded`] if too many passwords are being hashed, see
+/// the [`pool`](mod@pool) module.
 pub async fn hash_pwd(pwd: impl Into<String>, salt: impl Into<String>) -> Result<String> {
     hash_pwd_parts(PwdParts::new(pwd.into(), salt.into())).await
 }
@@ -29,26 +64,30 @@ pub async fn hash_pwd(pwd: impl Into<String>, salt: impl Into<String>) -> Result
 /// You can use this function together with the [`PwdParts::new`] method to
 /// create a password using the latest scheme.
 pub async fn hash_pwd_parts(pwd_parts: PwdParts) -> Result<String> {
-    tokio::task::spawn_blocking(move || {
-        get_scheme(&pwd_parts.scheme)?
-            .hash(&pwd_parts.pwd, &pwd_parts.salt)
-            .map(|hash| format!("#{}#{}", pwd_parts.scheme, hash))
-            .map_err(Error::SchemeError)
-    })
-    .await
-    .map_err(|_| Error::FailSpawnBlockForHash)
-    .and_then(|res| res)
+    pool()
+        .spawn(move || {
+            get_scheme(&pwd_parts.scheme)?
+                .hash(&pwd_parts.pwd, &pwd_parts.salt)
+                .map(|hash| format!("#{}#{}", pwd_parts.scheme, hash))
+                .map_err(Error::SchemeError)
+        })
+        .await?
+        .map_err(|_| Error::FailSpawnBlockForHash)
+        .and_then(|res| res)
 }
 
 /// Validate a hash against a password and a salt.
 ///
+/// Returns [`Error::Overloaded`] if too many passwords are being hashed, see
+/// the [`pool`](mod@pool) module.
+///
 /// The hash needs to be parseable into [`HashParts`]. See
 /// [`HashParts::from_str`] to see how the format works.
 pub async fn validate_pwd(
     pwd_hash: &str,
     pwd_ref: impl Into<String>,
     pwd_salt: Option<impl Into<String>>,
-) -> Result<bool> {
+) -> Result<PwdValidation> {
     let pwd_hash = HashParts::from_str(pwd_hash)?;
     let pwd_ref = pwd_ref.into();
     let pwd_salt = pwd_salt.map(|v| v.into());
@@ -72,35 +111,97 @@ pub async fn validate_pwd_parts(
     hash_parts: impl Into<HashParts>,
     pwd_ref: impl Into<String>,
     pwd_salt: Option<impl Into<String>>,
-) -> Result<bool> {
+) -> Result<PwdValidation> {
     let hash_parts = hash_parts.into();
     let pwd_ref = pwd_ref.into();
     let pwd_salt = pwd_salt.map(|v| v.into());
 
-    tokio::task::spawn_blocking(move || {
-        get_scheme(&hash_parts.scheme)?
-            .validate(&hash_parts.hash, &pwd_ref, pwd_salt.as_deref())
-            .map_err(Error::SchemeError)
-    })
-    .await
-    .map_err(|_| Error::FailSpawnBlockForValidate)
-    .and_then(|res| res)
+    pool()
+        .spawn(move || {
+            let scheme = get_scheme(&hash_parts.scheme)?;
+
+            if !scheme.validate(&hash_parts.hash, &pwd_ref, pwd_salt.as_deref())? {
+                return Ok(PwdValidation::Invalid);
+            }
+
+            if hash_parts.scheme != default_scheme() || scheme.needs_rehash(&hash_parts.hash) {
+                Ok(PwdValidation::Outdated)
+            } else {
+                Ok(PwdValidation::Valid)
+            }
+        })
+        .await?
+        .map_err(|_| Error::FailSpawnBlockForValidate)
+        .and_then(|res| res)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{hash_pwd, validate_pwd};
+    use super::{PwdValidation, hash_pwd, validate_pwd};
 
     #[tokio::test]
     async fn test_password_hashing_and_validate() {
-        dotenvy::from_filename(".env.test").unwrap();
+        dotenvy::from_filename(".env.test").ok();
 
         let salt = uuid::Uuid::new_v4().to_string();
         let hash = hash_pwd("password".to_string(), salt.clone())
             .await
             .unwrap();
 
-        assert!(!validate_pwd(&hash, "drowssap", Some(&salt)).await.unwrap());
-        assert!(validate_pwd(&hash, "password", Some(&salt)).await.unwrap());
+        assert_eq!(
+            validate_pwd(&hash, "drowssap", Some(&salt)).await.unwrap(),
+            PwdValidation::Invalid
+        );
+        assert_eq!(
+            validate_pwd(&hash, "password", Some(&salt)).await.unwrap(),
+            PwdValidation::Valid
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_outdated() {
+        use argon2::{
+            Algorithm, Argon2, Params, PasswordHasher, Version, password_hash::SaltString,
+        };
+
+        let weak = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8, 1, 1, None).unwrap(),
+        );
+        let salt = SaltString::encode_b64(b"saltsalt").unwrap();
+        let hash = format!("#01#{}", weak.hash_password(b"password", &salt).unwrap());
+        let hash = hash.as_str();
+
+        assert_eq!(
+            validate_pwd(hash, "password", None::<String>)
+                .await
+                .unwrap(),
+            PwdValidation::Outdated
+        );
+        assert_eq!(
+            validate_pwd(hash, "drowssap", None::<String>)
+                .await
+                .unwrap(),
+            PwdValidation::Invalid
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_imported() {
+        let hash = format!("#bcrypt#{}", bcrypt::hash("password", 4).unwrap());
+
+        assert_eq!(
+            validate_pwd(&hash, "password", None::<String>)
+                .await
+                .unwrap(),
+            PwdValidation::Outdated
+        );
+        assert_eq!(
+            validate_pwd(&hash, "drowssap", None::<String>)
+                .await
+                .unwrap(),
+            PwdValidation::Invalid
+        );
     }
 }
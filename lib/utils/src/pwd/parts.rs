@@ -1,7 +1,7 @@
 use regex::Regex;
 use std::{str::FromStr, sync::LazyLock};
 
-use super::{DEFAULT_SCHEME, error::Error};
+use super::{default_scheme, error::Error};
 
 /// A regex that turns a password hash into its parts.
 static PWD_PARTS_REGEX: LazyLock<Regex> =
@@ -27,7 +27,7 @@ impl PwdParts {
     /// This will have the latest scheme for hashing.
     pub fn new(pwd: String, salt: String) -> Self {
         Self {
-            scheme: DEFAULT_SCHEME.into(),
+            scheme: default_scheme(),
             salt,
             pwd,
         }
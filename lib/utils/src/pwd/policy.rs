@@ -0,0 +1,323 @@
+//! Rules new passwords have to follow.
+//!
+//! A password is rejected if it is too easy to guess, if it contains the
+//! username or email of the user, or if it shows up in a corpus of breached
+//! passwords. The policy is loaded from these environment variables:
+//!
+//! - `PWD_MIN_ENTROPY`: the least number of bits of entropy a password needs,
+//!   as estimated by [`estimate_entropy`]. Defaults to
+//!   [`DEFAULT_MIN_ENTROPY`].
+//! - `PWD_BREACH_CORPUS`: path to a [`BreachCorpus`] directory. Breach
+//!   checking is disabled when it is unset.
+//!
+//! The rules are exposed as [`validator`] functions, so they are reported like
+//! any other validation error.
+
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+    sync::LazyLock,
+};
+
+use ring::digest::{SHA1_FOR_LEGACY_USE_ONLY, digest};
+use validator::ValidationError;
+
+use crate::env::get_env_opt;
+
+use super::error::{Error, Result};
+
+/// The least entropy a password needs when `PWD_MIN_ENTROPY` isn't set.
+pub const DEFAULT_MIN_ENTROPY: f64 = 40.0;
+
+/// The shortest username or email part that is checked for in passwords.
+const MIN_PERSONAL_LEN: usize = 3;
+
+/// The policy configured from the environment.
+static POLICY: LazyLock<PwdPolicy> = LazyLock::new(|| {
+    PwdPolicy::from_env().unwrap_or_else(|err| panic!("invalid password policy: {err}"))
+});
+
+/// Rules new passwords have to follow.
+pub struct PwdPolicy {
+    min_entropy: f64,
+    breach_corpus: Option<BreachCorpus>,
+}
+
+impl PwdPolicy {
+    /// Creates a new [`PwdPolicy`] without breach checking.
+    pub fn new(min_entropy: f64) -> Self {
+        Self {
+            min_entropy,
+            breach_corpus: None,
+        }
+    }
+
+    /// Rejects passwords that are in the given corpus.
+    pub fn with_breach_corpus(mut self, corpus: BreachCorpus) -> Self {
+        self.breach_corpus = Some(corpus);
+        self
+    }
+
+    /// Creates a new [`PwdPolicy`] from environment variables.
+    ///
+    /// See the [module documentation](self) for the variables used.
+    pub fn from_env() -> Result<Self> {
+        let policy = Self::new(get_env_opt("PWD_MIN_ENTROPY")?.unwrap_or(DEFAULT_MIN_ENTROPY));
+
+        match get_env_opt::<_, PathBuf>("PWD_BREACH_CORPUS")? {
+            Some(path) => Ok(policy.with_breach_corpus(BreachCorpus::open(path)?)),
+            None => Ok(policy),
+        }
+    }
+
+    /// Checks a password against the policy.
+    ///
+    /// `personal` holds things like the username and email of the user, which
+    /// the password is not allowed to contain.
+    pub fn check(&self, pwd: &str, personal: &[&str]) -> std::result::Result<(), ValidationError> {
+        if contains_personal(pwd, personal) {
+            return Err(policy_error(
+                "password_personal",
+                "Password can't contain your username or email",
+            ));
+        }
+
+        if estimate_entropy(pwd) < self.min_entropy {
+            return Err(policy_error(
+                "password_weak",
+                "Password is too easy to guess",
+            ));
+        }
+
+        if let Some(corpus) = &self.breach_corpus
+            && corpus.contains(pwd)
+        {
+            return Err(policy_error(
+                "password_breached",
+                "Password has appeared in a data breach",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the policy configured from the environment.
+///
+/// The policy is loaded on the first call, which panics if it is invalid.
+pub fn policy() -> &'static PwdPolicy {
+    &POLICY
+}
+
+/// Validates a password against the configured policy.
+///
+/// Meant for `#[validate(custom(function = "..."))]` on a password field.
+pub fn validate_pwd_policy(pwd: &str) -> std::result::Result<(), ValidationError> {
+    policy().check(pwd, &[])
+}
+
+/// Validates a password against the configured policy, rejecting it if it
+/// contains any of the `personal` values.
+///
+/// Meant to be called from a `#[validate(schema(function = "..."))]`
+/// function, as it needs other fields than the password. The error is tagged
+/// with the `field` it belongs to, so it is reported on that field instead of
+/// on the whole request.
+pub fn validate_pwd_policy_for(
+    field: &'static str,
+    pwd: &str,
+    personal: &[&str],
+) -> std::result::Result<(), ValidationError> {
+    policy().check(pwd, personal).map_err(|mut err| {
+        err.add_param(Cow::Borrowed("field"), &field);
+        err
+    })
+}
+
+/// Estimates how many bits of entropy a password has.
+///
+/// Every character adds the bits needed to pick it from the character classes
+/// used in the password. Characters that repeat the previous one or continue
+/// a sequence, like `aaa` or `123`, only add a quarter of that.
+pub fn estimate_entropy(pwd: &str) -> f64 {
+    let has = |is_class: fn(&char) -> bool| pwd.chars().any(|c| is_class(&c));
+    let mut pool = 0;
+    if has(char::is_ascii_lowercase) {
+        pool += 26;
+    }
+    if has(char::is_ascii_uppercase) {
+        pool += 26;
+    }
+    if has(char::is_ascii_digit) {
+        pool += 10;
+    }
+    if has(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if !pwd.is_ascii() {
+        pool += 100;
+    }
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let bits = f64::from(pool).log2();
+    let mut entropy = 0.0;
+    let mut prev: Option<char> = None;
+    for c in pwd.chars() {
+        let predictable = prev.is_some_and(|prev| (c as i64 - prev as i64).abs() <= 1);
+        entropy += if predictable { bits / 4.0 } else { bits };
+        prev = Some(c);
+    }
+
+    entropy
+}
+
+/// Whether the password contains any of the personal values, ignoring case.
+///
+/// Emails are checked both whole and by their local part.
+fn contains_personal(pwd: &str, personal: &[&str]) -> bool {
+    let pwd = pwd.to_lowercase();
+
+    personal
+        .iter()
+        .flat_map(|value| [Some(*value), value.split_once('@').map(|(local, _)| local)])
+        .flatten()
+        .filter(|value| value.chars().count() >= MIN_PERSONAL_LEN)
+        .any(|value| pwd.contains(&value.to_lowercase()))
+}
+
+/// Creates a [`ValidationError`] with a message.
+fn policy_error(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::Borrowed(message))
+}
+
+/// A local corpus of SHA-1 hashes of breached passwords.
+///
+/// The corpus is a directory with a file for each first 5 hex characters of
+/// the hashes, like `5BAA6.txt`, holding the rest of each hash on a line,
+/// optionally followed by a colon and a count. That is what the k-anonymity
+/// range API of Have I Been Pwned returns, and what its downloader writes when
+/// it isn't told to write a single file. Only the file for the password being
+/// checked is read, so the corpus is never loaded into memory, and it can be
+/// swapped for the remote API without changing how it's used.
+pub struct BreachCorpus {
+    dir: PathBuf,
+}
+
+impl BreachCorpus {
+    /// Opens a corpus directory.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        if !std::fs::metadata(&dir)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{} isn't a directory", dir.display()),
+            )
+            .into());
+        }
+
+        Ok(Self { dir })
+    }
+
+    /// Returns the hashes starting with the given 5 hex characters, without
+    /// the prefix.
+    ///
+    /// Lines that aren't the rest of a SHA-1 hash are skipped.
+    pub fn range(&self, prefix: &str) -> Result<Vec<String>> {
+        if prefix.len() != 5 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::InvalidHashPrefix(prefix.into()));
+        }
+
+        let path = self
+            .dir
+            .join(format!("{}.txt", prefix.to_ascii_uppercase()));
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut suffixes = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let suffix = line.split(':').next().unwrap_or_default().trim();
+            if suffix.len() == 35 && suffix.chars().all(|c| c.is_ascii_hexdigit()) {
+                suffixes.push(suffix.to_ascii_uppercase());
+            }
+        }
+
+        Ok(suffixes)
+    }
+
+    /// Whether the password is in the corpus.
+    ///
+    /// A range file that can't be read counts as not containing the password,
+    /// so a broken corpus doesn't stop anyone from setting a password.
+    pub fn contains(&self, pwd: &str) -> bool {
+        let hash = encode_hex(digest(&SHA1_FOR_LEGACY_USE_ONLY, pwd.as_bytes()).as_ref());
+        let (prefix, suffix) = hash.split_at(5);
+
+        self.range(prefix)
+            .is_ok_and(|range| range.iter().any(|candidate| candidate == suffix))
+    }
+}
+
+/// Encodes bytes as uppercase hex.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-1 of "password".
+    const PASSWORD_SHA1: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    #[test]
+    fn test_estimate_entropy() {
+        assert_eq!(estimate_entropy(""), 0.0);
+        assert!(estimate_entropy("aaaaaaaa") < estimate_entropy("abzkqwpe"));
+        assert!(estimate_entropy("12345678") < 20.0);
+        assert!(estimate_entropy("correct horse battery staple") > DEFAULT_MIN_ENTROPY);
+    }
+
+    #[test]
+    fn test_personal() {
+        let policy = PwdPolicy::new(0.0);
+        let personal = ["alice", "alice.smith@example.com"];
+
+        assert!(policy.check("hunter2!ALICE", &personal).is_err());
+        assert!(policy.check("xalice.smithx", &personal).is_err());
+        assert!(policy.check("hunter2!", &personal).is_ok());
+        assert!(policy.check("hunter2!", &["ab"]).is_ok());
+    }
+
+    #[test]
+    fn test_breach_corpus() {
+        let dir = std::env::temp_dir().join(format!("lerpz-breach-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(
+            dir.join("5BAA6.txt"),
+            format!("{}:9545824\nnot a hash\n", &PASSWORD_SHA1[5..]),
+        )
+        .unwrap();
+        let corpus = BreachCorpus::open(&dir).unwrap();
+
+        assert_eq!(corpus.range("5baa6").unwrap(), [&PASSWORD_SHA1[5..]]);
+        assert!(corpus.range("5BAA7").unwrap().is_empty());
+        assert!(corpus.range("nope").is_err());
+        assert!(corpus.contains("password"));
+        assert!(!corpus.contains("Password"));
+        assert!(BreachCorpus::open(dir.join("5BAA6.txt")).is_err());
+
+        let policy = PwdPolicy::new(0.0).with_breach_corpus(corpus);
+        let err = policy.check("password", &[]).unwrap_err();
+        assert_eq!(err.code, "password_breached");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
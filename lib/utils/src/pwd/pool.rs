@@ -0,0 +1,131 @@
+//! A bounded pool for hashing and validating passwords.
+//!
+//! Hashing takes a lot of memory, so only a limited number of passwords are
+//! hashed at once, and only a limited number are allowed to wait for their
+//! turn. Anything above that is turned away with [`Error::Overloaded`] right
+//! away, instead of piling up until the server runs out of memory. The limits
+//! are loaded from these environment variables:
+//!
+//! - `PWD_POOL_CONCURRENCY`: how many passwords are hashed at once. Defaults
+//!   to the number of CPUs.
+//! - `PWD_POOL_QUEUE`: how many passwords can wait to be hashed. Defaults to
+//!   [`DEFAULT_QUEUE_DEPTH`].
+
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, LazyLock},
+    thread::available_parallelism,
+};
+
+use tokio::{sync::Semaphore, task::JoinError};
+
+use crate::env::get_env_opt;
+
+use super::error::{Error, Result};
+
+/// How many passwords can wait to be hashed when `PWD_POOL_QUEUE` isn't set.
+pub const DEFAULT_QUEUE_DEPTH: usize = 64;
+
+/// The pool configured from the environment.
+static POOL: LazyLock<HashPool> = LazyLock::new(|| {
+    HashPool::from_env().unwrap_or_else(|err| panic!("invalid password hashing pool: {err}"))
+});
+
+/// Runs blocking password work with bounded concurrency and queue depth.
+#[derive(Clone)]
+pub struct HashPool {
+    /// Permits to run, one for each password being hashed.
+    workers: Arc<Semaphore>,
+    /// Permits to enter the pool, one for each password being hashed or
+    /// waiting to be.
+    slots: Arc<Semaphore>,
+}
+
+impl HashPool {
+    /// Creates a new [`HashPool`] that hashes `max_concurrency` passwords at
+    /// once and lets `queue_depth` more wait.
+    pub fn new(max_concurrency: NonZeroUsize, queue_depth: usize) -> Self {
+        let max_concurrency = max_concurrency.get();
+        Self {
+            workers: Arc::new(Semaphore::new(max_concurrency)),
+            slots: Arc::new(Semaphore::new(max_concurrency + queue_depth)),
+        }
+    }
+
+    /// Creates a new [`HashPool`] from environment variables.
+    ///
+    /// See the [module documentation](self) for the variables used.
+    pub fn from_env() -> Result<Self> {
+        let max_concurrency = match get_env_opt("PWD_POOL_CONCURRENCY")? {
+            Some(max_concurrency) => max_concurrency,
+            None => available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        };
+        let queue_depth = get_env_opt("PWD_POOL_QUEUE")?.unwrap_or(DEFAULT_QUEUE_DEPTH);
+
+        Ok(Self::new(max_concurrency, queue_depth))
+    }
+
+    /// Runs `f` on a blocking thread once it's its turn.
+    ///
+    /// Returns [`Error::Overloaded`] if the queue is full, and the
+    /// [`JoinError`] if `f` panics. The permits are held by the blocking
+    /// thread, so work that is given up on still counts until it's done.
+    pub async fn spawn<F, T>(&self, f: F) -> Result<std::result::Result<T, JoinError>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let slot = self
+            .slots
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Error::Overloaded)?;
+        let worker = self
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Overloaded)?;
+
+        Ok(tokio::task::spawn_blocking(move || {
+            let _permits = (slot, worker);
+            f()
+        })
+        .await)
+    }
+}
+
+/// Returns the pool configured from the environment.
+///
+/// The pool is loaded on the first call, which panics if it is invalid.
+pub fn pool() -> &'static HashPool {
+    &POOL
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_overloaded() {
+        let pool = HashPool::new(NonZeroUsize::MIN, 0);
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let running = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.spawn(move || rx.recv().unwrap()).await }
+        });
+        while pool.slots.available_permits() > 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(matches!(pool.spawn(|| ()).await, Err(Error::Overloaded)));
+
+        tx.send(()).unwrap();
+        running.await.unwrap().unwrap().unwrap();
+
+        assert_eq!(pool.spawn(|| 1).await.unwrap().unwrap(), 1);
+    }
+}
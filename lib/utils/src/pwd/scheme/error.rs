@@ -13,6 +13,20 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
 	#[error("no scheme named \"{0}\" exist")]
 	SchemeNotFound(String),
+	#[error("\"{0}\" isn't a valid scheme ID")]
+	InvalidSchemeId(String),
+	#[error("a scheme named \"{0}\" is already registered")]
+	SchemeExists(String),
 	#[error("error hashing password: {0}")]
 	PwdHash(#[from] Argon2Error),
+	#[error("invalid argon2 parameters: {0}")]
+	Params(argon2::Error),
+	#[error("invalid scheme configuration: {0}")]
+	Config(#[from] crate::env::Error),
+	#[error("scheme \"{0}\" can only validate passwords")]
+	ValidateOnly(&'static str),
+	#[error("scheme \"{0}\" can't hash passwords, so it can't be the default")]
+	CantHash(String),
+	#[error("error validating bcrypt hash: {0}")]
+	Bcrypt(#[from] bcrypt::BcryptError),
 }
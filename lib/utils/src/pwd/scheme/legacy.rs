@@ -0,0 +1,129 @@
+//! Schemes for validating hashes imported from other systems.
+//!
+//! These schemes can only validate passwords. Hashes made with them are always
+//! outdated, so users are moved to the default scheme the first time they log
+//! in.
+//!
+//! | ID       | Format                                     |
+//! |----------|--------------------------------------------|
+//! | `bcrypt` | `$2a$`, `$2b$`, `$2x$` and `$2y$` hashes   |
+//! | `pbkdf2` | PHC strings, e.g. `$pbkdf2-sha256$i=...`   |
+//! | `scrypt` | PHC strings, e.g. `$scrypt$ln=...`         |
+
+use argon2::{PasswordHash, PasswordVerifier};
+use pbkdf2::Pbkdf2 as Pbkdf2Hasher;
+use scrypt::Scrypt as ScryptHasher;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+};
+
+/// Validates bcrypt hashes.
+pub struct Bcrypt;
+
+/// Validates PBKDF2 hashes using SHA-1, SHA-256 or SHA-512.
+pub struct Pbkdf2;
+
+/// Validates scrypt hashes.
+pub struct Scrypt;
+
+impl Scheme for Bcrypt {
+    fn hash(&self, _: &str, _: &str) -> Result<String> {
+        Err(Error::ValidateOnly("bcrypt"))
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str, _: Option<&str>) -> Result<bool> {
+        Ok(bcrypt::verify(pwd_ref, pwd_hash)?)
+    }
+}
+
+impl Scheme for Pbkdf2 {
+    fn hash(&self, _: &str, _: &str) -> Result<String> {
+        Err(Error::ValidateOnly("pbkdf2"))
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str, _: Option<&str>) -> Result<bool> {
+        let pwd_hash_parsed = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
+
+        Ok(Pbkdf2Hasher
+            .verify_password(pwd_ref.as_bytes(), &pwd_hash_parsed)
+            .is_ok())
+    }
+}
+
+impl Scheme for Scrypt {
+    fn hash(&self, _: &str, _: &str) -> Result<String> {
+        Err(Error::ValidateOnly("scrypt"))
+    }
+
+    fn can_hash(&self) -> bool {
+        false
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str, _: Option<&str>) -> Result<bool> {
+        let pwd_hash_parsed = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
+
+        Ok(ScryptHasher
+            .verify_password(pwd_ref.as_bytes(), &pwd_hash_parsed)
+            .is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::{PasswordHasher, password_hash::SaltString};
+
+    use super::*;
+
+    #[test]
+    fn test_bcrypt() {
+        let hash = bcrypt::hash("password", 4).unwrap();
+
+        assert!(Bcrypt.validate(&hash, "password", None).unwrap());
+        assert!(!Bcrypt.validate(&hash, "drowssap", None).unwrap());
+        assert!(Bcrypt.validate("not a hash", "password", None).is_err());
+        assert!(matches!(
+            Bcrypt.hash("password", "saltsalt"),
+            Err(Error::ValidateOnly(_))
+        ));
+    }
+
+    #[test]
+    fn test_pbkdf2() {
+        let salt = SaltString::encode_b64(b"saltsalt").unwrap();
+        let params = pbkdf2::Params {
+            rounds: 1000,
+            output_length: 32,
+        };
+        let hash = Pbkdf2Hasher
+            .hash_password_customized(b"password", None, None, params, &salt)
+            .unwrap()
+            .to_string();
+
+        assert!(Pbkdf2.validate(&hash, "password", None).unwrap());
+        assert!(!Pbkdf2.validate(&hash, "drowssap", None).unwrap());
+    }
+
+    #[test]
+    fn test_scrypt() {
+        let salt = SaltString::encode_b64(b"saltsalt").unwrap();
+        let params = scrypt::Params::new(4, 8, 1, 32).unwrap();
+        let hash = ScryptHasher
+            .hash_password_customized(b"password", None, None, params, &salt)
+            .unwrap()
+            .to_string();
+
+        assert!(Scrypt.validate(&hash, "password", None).unwrap());
+        assert!(!Scrypt.validate(&hash, "drowssap", None).unwrap());
+        assert!(!Pbkdf2.validate(&hash, "password", None).unwrap());
+    }
+}
@@ -2,25 +2,35 @@
 
 /// Hashing and validation schemes errors.
 pub mod error;
+/// Validate-only schemes for hashes imported from other systems.
+pub mod legacy;
+/// The schemes that can be looked up by their ID.
+mod registry;
 /// Password scheme that uses argon2.
 pub mod scheme_01;
+/// Password scheme that uses argon2 with configurable parameters and pepper.
+pub mod scheme_02;
 
-use error::{Error, Result};
-use scheme_01::Scheme01;
+use error::Result;
+
+pub use registry::{
+    BUILTIN_DEFAULT_SCHEME, default_scheme, get_scheme, init, register_scheme, set_default_scheme,
+};
 
 /// Implemented by schemes that can hash and validate passwords.
-pub trait Scheme {
+pub trait Scheme: Send + Sync {
     /// Hashes a password from some [`PwdParts`](super::parts::PwdParts).
     fn hash(&self, pwd: &str, salt: &str) -> Result<String>;
     /// Validate a password hash against a real password.
     fn validate(&self, pwd_hash: &str, pwd_ref: &str, pwd_ref_salt: Option<&str>) -> Result<bool>;
-}
-
-/// Returns a scheme given a scheme name as a string.
-#[inline]
-pub fn get_scheme(scheme_name: &str) -> Result<impl Scheme> {
-    match scheme_name {
-        "01" => Ok(Scheme01),
-        _ => Err(Error::SchemeNotFound(scheme_name.into())),
+    /// Whether a hash made by this scheme uses other parameters than the ones
+    /// the scheme hashes with now.
+    fn needs_rehash(&self, _pwd_hash: &str) -> bool {
+        false
+    }
+    /// Whether the scheme can hash new passwords, and not only validate
+    /// existing hashes. Only such schemes can be the default.
+    fn can_hash(&self) -> bool {
+        true
     }
 }
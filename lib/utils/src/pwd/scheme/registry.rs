@@ -0,0 +1,180 @@
+//! A registry of the schemes passwords can be hashed and validated with.
+//!
+//! The registry starts out with the schemes in this crate, and applications
+//! can add their own at startup, e.g. to validate hashes imported from another
+//! system. The default scheme is taken from the `PWD_DEFAULT_SCHEME`
+//! environment variable, falling back to [`BUILTIN_DEFAULT_SCHEME`]. It is
+//! only looked up once it's needed, so it can name a scheme the application
+//! registers, and [`init`] should be called once those are registered to make
+//! sure it exists.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, PoisonError, RwLock},
+};
+
+use crate::env::get_env_opt;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+    legacy::{Bcrypt, Pbkdf2, Scrypt},
+    scheme_01::Scheme01,
+    scheme_02::SCHEME_02,
+};
+
+/// The default scheme used when `PWD_DEFAULT_SCHEME` isn't set.
+pub const BUILTIN_DEFAULT_SCHEME: &str = "02";
+
+/// The registered schemes and which one of them is the default.
+struct Registry {
+    schemes: HashMap<String, &'static dyn Scheme>,
+    /// Taken from the environment the first time it's needed.
+    default: Option<String>,
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| {
+    let mut schemes = HashMap::<String, &'static dyn Scheme>::new();
+    schemes.insert("01".into(), &Scheme01);
+    schemes.insert("02".into(), &*SCHEME_02);
+    schemes.insert("bcrypt".into(), &Bcrypt);
+    schemes.insert("pbkdf2".into(), &Pbkdf2);
+    schemes.insert("scrypt".into(), &Scrypt);
+
+    RwLock::new(Registry {
+        schemes,
+        default: None,
+    })
+});
+
+/// Checks that the default scheme exists and can hash passwords.
+///
+/// Meant to be called at startup, after the application has registered its
+/// own schemes, so that a bad `PWD_DEFAULT_SCHEME` is reported right away
+/// instead of on the first password that is hashed.
+pub fn init() -> Result<()> {
+    set_default_scheme(&default_scheme())
+}
+
+/// Returns a scheme given a scheme name as a string.
+pub fn get_scheme(scheme_name: &str) -> Result<&'static dyn Scheme> {
+    let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+    registry
+        .schemes
+        .get(scheme_name)
+        .copied()
+        .ok_or_else(|| Error::SchemeNotFound(scheme_name.into()))
+}
+
+/// Registers a scheme under the given ID.
+///
+/// The ID is what goes between the `#`s in front of a hash, so it can only
+/// contain letters, digits and underscores, and it can't already be taken, as
+/// replacing a scheme would change how existing hashes are validated.
+///
+/// Schemes are meant to be registered once at startup and live for the rest
+/// of the program, so the scheme is leaked.
+pub fn register_scheme(id: impl Into<String>, scheme: Box<dyn Scheme>) -> Result<()> {
+    let id = id.into();
+    if id.is_empty() || !id.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(Error::InvalidSchemeId(id));
+    }
+
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    if registry.schemes.contains_key(&id) {
+        return Err(Error::SchemeExists(id));
+    }
+    registry.schemes.insert(id, Box::leak(scheme));
+    Ok(())
+}
+
+/// Returns the ID of the scheme new passwords are hashed with.
+///
+/// The ID isn't checked here, so hashing fails with
+/// [`Error::SchemeNotFound`] if it doesn't exist. Use [`init`] to check it up
+/// front.
+pub fn default_scheme() -> String {
+    let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+    if let Some(default) = &registry.default {
+        return default.clone();
+    }
+    drop(registry);
+
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    registry
+        .default
+        .get_or_insert_with(|| {
+            get_env_opt("PWD_DEFAULT_SCHEME")
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| BUILTIN_DEFAULT_SCHEME.to_string())
+        })
+        .clone()
+}
+
+/// Sets the scheme new passwords are hashed with.
+///
+/// The scheme has to be registered already, and it has to be able to hash
+/// passwords. Passwords hashed with any other scheme will be reported as
+/// outdated when they are validated.
+pub fn set_default_scheme(id: &str) -> Result<()> {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    match registry.schemes.get(id) {
+        None => return Err(Error::SchemeNotFound(id.into())),
+        Some(scheme) if !scheme.can_hash() => return Err(Error::CantHash(id.into())),
+        Some(_) => {}
+    }
+    registry.default = Some(id.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Plain;
+
+    impl Scheme for Plain {
+        fn hash(&self, pwd: &str, _: &str) -> Result<String> {
+            Ok(pwd.into())
+        }
+
+        fn validate(&self, pwd_hash: &str, pwd_ref: &str, _: Option<&str>) -> Result<bool> {
+            Ok(pwd_hash == pwd_ref)
+        }
+    }
+
+    #[test]
+    fn test_register_scheme() {
+        assert!(matches!(
+            get_scheme("test_plain"),
+            Err(Error::SchemeNotFound(_))
+        ));
+
+        register_scheme("test_plain", Box::new(Plain)).unwrap();
+        let scheme = get_scheme("test_plain").unwrap();
+        assert!(scheme.validate("password", "password", None).unwrap());
+        assert!(!scheme.validate("password", "drowssap", None).unwrap());
+
+        assert!(matches!(
+            register_scheme("not#valid", Box::new(Plain)),
+            Err(Error::InvalidSchemeId(_))
+        ));
+        assert!(matches!(
+            register_scheme("test_plain", Box::new(Plain)),
+            Err(Error::SchemeExists(_))
+        ));
+        assert!(matches!(
+            register_scheme("02", Box::new(Plain)),
+            Err(Error::SchemeExists(_))
+        ));
+        assert!(matches!(
+            set_default_scheme("missing"),
+            Err(Error::SchemeNotFound(_))
+        ));
+        assert!(matches!(
+            set_default_scheme("bcrypt"),
+            Err(Error::CantHash(_))
+        ));
+    }
+}
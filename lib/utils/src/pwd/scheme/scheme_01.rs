@@ -2,14 +2,17 @@
 
 use std::sync::LazyLock;
 
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::SaltString,
+};
 
 use super::{
     Scheme,
     error::{Error, Result},
 };
 
-static ARGON2: LazyLock<Argon2<'static>> = LazyLock::new(|| Argon2::default());
+static ARGON2: LazyLock<Argon2<'static>> = LazyLock::new(Argon2::default);
 
 pub struct Scheme01;
 
@@ -34,4 +37,43 @@ impl Scheme for Scheme01 {
             .verify_password(pwd_ref_bytes, &pwd_hash_parsed)
             .is_ok())
     }
+
+    fn needs_rehash(&self, pwd_hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(pwd_hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+        let current = ARGON2.params();
+
+        hash.algorithm != Algorithm::default().ident()
+            || hash.version != Some(Version::default().into())
+            || params.m_cost() != current.m_cost()
+            || params.t_cost() != current.t_cost()
+            || params.p_cost() != current.p_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_rehash() {
+        let hash = Scheme01.hash("password", "saltsalt").unwrap();
+        assert!(!Scheme01.needs_rehash(&hash));
+
+        let weak = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8, 1, 1, None).unwrap(),
+        );
+        let salt = SaltString::encode_b64(b"saltsalt").unwrap();
+        let hash = weak.hash_password(b"password", &salt).unwrap().to_string();
+        assert!(Scheme01.needs_rehash(&hash));
+        assert!(Scheme01.validate(&hash, "password", None).unwrap());
+
+        assert!(Scheme01.needs_rehash("not a hash"));
+    }
 }
@@ -0,0 +1,239 @@
+//! Scheme 02 implemented using Argon2 with configurable parameters.
+//!
+//! The parameters and an optional pepper are loaded from these environment
+//! variables, falling back to the Argon2 defaults when they are unset:
+//!
+//! - `PWD_ARGON2_M_COST`: memory size in KiB.
+//! - `PWD_ARGON2_T_COST`: number of iterations.
+//! - `PWD_ARGON2_P_COST`: degree of parallelism.
+//! - `PWD_PEPPER`: a server-side secret that passwords are keyed with using
+//!   HMAC-SHA256 before they are hashed.
+//!
+//! Changing the parameters only makes old hashes outdated, but changing or
+//! removing the pepper makes every hash made with it invalid.
+
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::SaltString,
+};
+use ring::hmac;
+
+use crate::env::get_env_opt;
+
+use super::{
+    Scheme,
+    error::{Error, Result},
+};
+
+/// Scheme 02 configured from the environment.
+pub(super) static SCHEME_02: LazyLock<Scheme02> = LazyLock::new(|| {
+    Scheme02::from_env()
+        .unwrap_or_else(|err| panic!("invalid configuration for password scheme 02: {err}"))
+});
+
+/// The most iterations [`calibrate`] will try before giving up.
+const MAX_T_COST: u32 = 64;
+
+/// Argon2id with configurable parameters and an optional pepper.
+pub struct Scheme02 {
+    argon2: Argon2<'static>,
+    pepper: Option<hmac::Key>,
+}
+
+impl Scheme02 {
+    /// Creates a new [`Scheme02`] from Argon2 parameters and an optional
+    /// pepper.
+    pub fn new(params: Params, pepper: Option<&[u8]>) -> Self {
+        Self {
+            argon2: Argon2::new(Algorithm::Argon2id, Version::V0x13, params),
+            pepper: pepper.map(|pepper| hmac::Key::new(hmac::HMAC_SHA256, pepper)),
+        }
+    }
+
+    /// Creates a new [`Scheme02`] from environment variables.
+    ///
+    /// See the [module documentation](self) for the variables used.
+    pub fn from_env() -> Result<Self> {
+        let params = Params::new(
+            get_env_opt("PWD_ARGON2_M_COST")?.unwrap_or(Params::DEFAULT_M_COST),
+            get_env_opt("PWD_ARGON2_T_COST")?.unwrap_or(Params::DEFAULT_T_COST),
+            get_env_opt("PWD_ARGON2_P_COST")?.unwrap_or(Params::DEFAULT_P_COST),
+            None,
+        )
+        .map_err(Error::Params)?;
+        let pepper: Option<String> = get_env_opt("PWD_PEPPER")?;
+
+        Ok(Self::new(params, pepper.as_deref().map(str::as_bytes)))
+    }
+
+    /// The Argon2 parameters this scheme hashes with.
+    pub fn params(&self) -> &Params {
+        self.argon2.params()
+    }
+
+    /// Keys the password with the pepper if there is one.
+    fn pepper(&self, pwd: &str) -> Vec<u8> {
+        match &self.pepper {
+            Some(key) => hmac::sign(key, pwd.as_bytes()).as_ref().to_vec(),
+            None => pwd.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Scheme for Scheme02 {
+    fn hash(&self, pwd: &str, salt: &str) -> Result<String> {
+        let salt = SaltString::encode_b64(salt.as_bytes()).map_err(Error::PwdHash)?;
+
+        let pwd = self
+            .argon2
+            .hash_password(&self.pepper(pwd), &salt)
+            .map_err(Error::PwdHash)?
+            .to_string();
+
+        Ok(pwd)
+    }
+
+    fn validate(&self, pwd_hash: &str, pwd_ref: &str, _: Option<&str>) -> Result<bool> {
+        let pwd_hash_parsed = PasswordHash::new(pwd_hash).map_err(Error::PwdHash)?;
+
+        Ok(self
+            .argon2
+            .verify_password(&self.pepper(pwd_ref), &pwd_hash_parsed)
+            .is_ok())
+    }
+
+    fn needs_rehash(&self, pwd_hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(pwd_hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+        let current = self.params();
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != current.m_cost()
+            || params.t_cost() != current.t_cost()
+            || params.p_cost() != current.p_cost()
+    }
+}
+
+/// Finds Argon2 parameters that take about `target` to hash a password with
+/// on the current machine.
+///
+/// Starting from `m_cost` KiB of memory, the number of iterations is raised
+/// until hashing takes longer than `target`, and the most iterations that
+/// stayed within it are used. If a single iteration is already too slow, the
+/// memory is halved until it isn't.
+///
+/// This is meant to be run by hand on the machine that will hash passwords,
+/// with the result put into the `PWD_ARGON2_*` environment variables.
+pub fn calibrate(target: Duration, m_cost: u32, p_cost: u32) -> Result<Params> {
+    let mut m_cost = m_cost.max(Params::MIN_M_COST);
+
+    loop {
+        let params = Params::new(m_cost, 1, p_cost, None).map_err(Error::Params)?;
+        if time_hash(params)? <= target || m_cost / 2 < 8 * p_cost.max(1) {
+            break;
+        }
+        m_cost /= 2;
+    }
+
+    let mut t_cost = 1;
+    while t_cost < MAX_T_COST {
+        let params = Params::new(m_cost, t_cost + 1, p_cost, None).map_err(Error::Params)?;
+        if time_hash(params)? > target {
+            break;
+        }
+        t_cost += 1;
+    }
+
+    Params::new(m_cost, t_cost, p_cost, None).map_err(Error::Params)
+}
+
+/// Measures how long it takes to hash a password with the given parameters.
+fn time_hash(params: Params) -> Result<Duration> {
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let salt = SaltString::encode_b64(b"calibration").map_err(Error::PwdHash)?;
+
+    let start = Instant::now();
+    argon2
+        .hash_password(b"calibration", &salt)
+        .map_err(Error::PwdHash)?;
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use argon2::password_hash::ParamsString;
+
+    /// The width of the `users.password_hash` column.
+    const PASSWORD_HASH_WIDTH: usize = 512;
+
+    fn weak() -> Params {
+        Params::new(Params::MIN_M_COST, 1, 1, None).unwrap()
+    }
+
+    #[test]
+    fn test_pepper() {
+        let peppered = Scheme02::new(weak(), Some(b"pepper"));
+        let hash = peppered.hash("password", "saltsalt").unwrap();
+
+        assert!(peppered.validate(&hash, "password", None).unwrap());
+        assert!(!peppered.validate(&hash, "drowssap", None).unwrap());
+        assert!(
+            !Scheme02::new(weak(), Some(b"other"))
+                .validate(&hash, "password", None)
+                .unwrap()
+        );
+        assert!(
+            !Scheme02::new(weak(), None)
+                .validate(&hash, "password", None)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let scheme = Scheme02::new(weak(), None);
+        let hash = scheme.hash("password", "saltsalt").unwrap();
+        assert!(!scheme.needs_rehash(&hash));
+
+        let stronger = Params::new(Params::MIN_M_COST, 2, 1, None).unwrap();
+        assert!(Scheme02::new(stronger, None).needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_hash_fits_column() {
+        let salt = uuid::Uuid::new_v4().to_string();
+        let hash = Scheme02::new(weak(), None).hash("password", &salt).unwrap();
+
+        // Hashing with the most memory isn't feasible, but only the length of
+        // the parameters matters.
+        let mut hash = PasswordHash::new(&hash).unwrap();
+        let largest =
+            Params::new(Params::MAX_M_COST, MAX_T_COST, Params::MAX_P_COST, None).unwrap();
+        hash.params = ParamsString::try_from(&largest).unwrap();
+
+        let hash = format!("#02#{hash}");
+        assert!(hash.len() > 128);
+        assert!(hash.len() <= PASSWORD_HASH_WIDTH);
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let params = calibrate(Duration::from_millis(5), 1024, 1).unwrap();
+
+        assert!(params.m_cost() <= 1024);
+        assert!(params.t_cost() >= 1);
+        assert_eq!(params.p_cost(), 1);
+    }
+}
@@ -0,0 +1,10 @@
+-- Public clients (such as single-page applications) can't keep a secret, so
+-- they are registered without one and have to use PKCE instead.
+ALTER TABLE oauth_clients ALTER COLUMN secret DROP NOT NULL;
+
+ALTER TABLE oauth_clients ADD COLUMN require_pkce BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE oauth_clients SET
+    secret = NULL,
+    require_pkce = TRUE
+WHERE id = 'cdd37e5a-a554-4535-bff2-45ba130b05b4';
@@ -0,0 +1,26 @@
+-- Refresh tokens are opaque, so only a SHA-256 hash of each token is stored.
+-- Every refresh rotates the token, and all tokens descending from the same
+-- grant share a family so that the whole chain can be revoked when a rotated
+-- token is presented again.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    family_id UUID NOT NULL,
+    client_id UUID NOT NULL REFERENCES oauth_clients(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    scope TEXT DEFAULT NULL,
+    access_token_jti VARCHAR(64) NOT NULL,
+    access_token_expires_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    rotated_at TIMESTAMPTZ DEFAULT NULL,
+    revoked_at TIMESTAMPTZ DEFAULT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
+
+CREATE TRIGGER update_timestamp
+    BEFORE UPDATE ON refresh_tokens
+    FOR EACH ROW
+    EXECUTE FUNCTION update_timestamp();
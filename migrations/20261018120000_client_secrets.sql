@@ -0,0 +1,20 @@
+-- Client secrets are hashed with the same schemes as user passwords. Secrets
+-- stored before this migration are in plaintext and will no longer validate,
+-- so confidential clients have to be issued a new secret with:
+--
+--     cargo run --bin lerpz-client-secret -- <client_id>
+--
+-- or `/var/app/lerpz-client-secret <client_id>` inside the container. The new
+-- secret is printed once and only its hash is stored.
+ALTER TABLE oauth_clients RENAME COLUMN secret TO secret_hash;
+
+ALTER TABLE oauth_clients ADD COLUMN secret_salt VARCHAR(64) DEFAULT NULL;
+
+-- The scopes a client may request. Tokens issued through the client
+-- credentials grant are limited to these.
+ALTER TABLE oauth_clients ADD COLUMN scopes TEXT[] NOT NULL DEFAULT '{}';
+
+-- The portal signs users in with OpenID Connect.
+UPDATE oauth_clients
+SET scopes = '{openid,profile,email}'
+WHERE id = 'cdd37e5a-a554-4535-bff2-45ba130b05b4';
@@ -0,0 +1,8 @@
+-- The grant types a client may use at the token endpoint. Confidential
+-- clients keep the client credentials grant they could use before.
+ALTER TABLE oauth_clients ADD COLUMN grant_types TEXT[] NOT NULL
+    DEFAULT '{authorization_code,refresh_token}';
+
+UPDATE oauth_clients SET
+    grant_types = '{authorization_code,client_credentials,refresh_token}'
+WHERE secret_hash IS NOT NULL;
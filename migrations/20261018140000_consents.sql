@@ -0,0 +1,15 @@
+-- The scopes a user has agreed to share with a client. The consent step of
+-- the authorization endpoint is skipped when these cover the requested scope.
+CREATE TABLE IF NOT EXISTS consents (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, client_id)
+);
+
+CREATE TRIGGER update_timestamp
+    BEFORE UPDATE ON consents
+    FOR EACH ROW
+    EXECUTE FUNCTION update_timestamp();
@@ -0,0 +1,5 @@
+-- The response types a client may use at the authorization endpoint. Only the
+-- authorization code flow is enabled by default, since the implicit and hybrid
+-- flows deliver tokens through the browser.
+ALTER TABLE oauth_clients ADD COLUMN response_types TEXT[] NOT NULL
+    DEFAULT '{code}';
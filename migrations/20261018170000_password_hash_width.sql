@@ -0,0 +1,3 @@
+-- Argon2 hashes with calibrated parameters and imported PBKDF2 hashes don't
+-- fit in 128 characters once the scheme prefix is added.
+ALTER TABLE users ALTER COLUMN password_hash TYPE VARCHAR(512);
@@ -1,6 +1,201 @@
-use lerpz_utils::axum::error::HandlerResult;
+//! Verification of the primary email of users.
+//!
+//! A single-use link is mailed to users when they register, or when they ask
+//! for a new one. Following the link marks the email as verified.
 
+use crate::{
+    config::CONFIG,
+    mail::{TEMPLATES, preferred_locales},
+    state::AppState,
+};
+
+use super::{frontend_url, oauth::generate_secret};
+
+use lerpz_core::db::EmailVerification;
+use lerpz_utils::axum::{
+    error::{HandlerError, HandlerResult},
+    middelware::validate::Validated,
+};
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::Redirect,
+};
+use chrono::{Duration, Utc};
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+use validator::Validate;
+
+/// How long a verification link is valid for, in seconds.
+const VERIFICATION_TTL: i64 = 60 * 60 * 24;
+
+/// How long to wait before another verification email can be sent to the
+/// same address, in seconds.
+const RESEND_INTERVAL: u64 = 60;
+
+/// Creates a verification token for the email and mails the link to it.
+///
+/// The mail is written in the first of the `locales` that is supported.
+pub async fn send_verification(
+    state: &AppState,
+    user_id: Uuid,
+    email: &str,
+    locales: &[String],
+) -> anyhow::Result<()> {
+    let token = generate_secret();
+    let expires_at = Utc::now() + Duration::seconds(VERIFICATION_TTL);
+
+    sqlx::query!(
+        "INSERT INTO email_verifications (
+        token_hash,
+        user_id,
+        email,
+        expires_at
+        ) VALUES ($1, $2, $3, $4)",
+        hash(&token),
+        user_id,
+        email,
+        expires_at
+    )
+    .execute(&state.database)
+    .await?;
+
+    let mut link = Url::parse(&CONFIG.ISSUER)?.join("/api/verify-email")?;
+    link.query_pairs_mut().append_pair("token", &token);
+
+    let mail = TEMPLATES.render("verify_email", locales, email, &[("link", link.as_str())])?;
+    state.mailer.send(&mail).await?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyEmailQuery {
+    token: String,
+}
+
+/// Marks the email a verification link was sent to as verified.
+///
+/// Redirects to the `verify-email` page of the frontend with the outcome.
+#[axum::debug_handler]
+pub async fn handler(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> HandlerResult<Redirect> {
+    let now = Utc::now();
+    let mut tx = state.database.begin().await?;
+
+    let verification = sqlx::query_as!(
+        EmailVerification,
+        "UPDATE email_verifications SET used_at = $2
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2
+        RETURNING id, token_hash, user_id, email, expires_at, used_at, created_at",
+        hash(&query.token),
+        now
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    // The email only counts as verified if the user still has the address
+    // the link was sent to.
+    let verified = match verification {
+        Some(verification) => {
+            sqlx::query!(
+                "UPDATE users SET email_verified_at = COALESCE(email_verified_at, $3)
+                WHERE id = $1 AND primary_email = $2",
+                verification.user_id,
+                verification.email,
+                now
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+                > 0
+        }
+        None => false,
+    };
+
+    tx.commit().await?;
+
+    let status = if verified { "verified" } else { "invalid" };
+    let url = frontend_url("verify-email", &[("status", status)]);
+    Ok(Redirect::to(url.as_str()))
+}
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct ResendRequest {
+    #[validate(email(message = "Invalid email format"))]
+    email: String,
+}
+
+/// Sends a new verification link to an unverified email.
+///
+/// The email is sent in the background, so that neither the response nor the
+/// time it takes can be used to find out which emails are registered.
 #[axum::debug_handler]
-pub async fn handler() -> HandlerResult<()> {
-    todo!()
+pub async fn resend(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Validated(Json(body)): Validated<Json<ResendRequest>>,
+) -> HandlerResult<StatusCode> {
+    let mut conn = state.redis.get_multiplexed_async_connection().await?;
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::EX(RESEND_INTERVAL));
+    let allowed: Option<String> = conn
+        .set_options(resend_key(&body.email), 1, options)
+        .await?;
+
+    if allowed.is_none() {
+        return Err(HandlerError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many requests",
+            "A verification email was sent recently, try again later.",
+        )
+        .with_header(header::RETRY_AFTER, HeaderValue::from(RESEND_INTERVAL)));
+    }
+
+    let locales = preferred_locales(&headers);
+    tokio::spawn(async move {
+        if let Err(err) = resend_verification(&state, &body.email, &locales).await {
+            tracing::error!(error = %err, "failed resending verification email");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn resend_verification(
+    state: &AppState,
+    email: &str,
+    locales: &[String],
+) -> anyhow::Result<()> {
+    let user = sqlx::query!(
+        "SELECT id FROM users WHERE primary_email = $1 AND email_verified_at IS NULL",
+        email
+    )
+    .fetch_optional(&state.database)
+    .await?;
+
+    if let Some(user) = user {
+        send_verification(state, user.id, email, locales).await?;
+    }
+
+    Ok(())
+}
+
+/// Hashes a verification token for storage.
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token))
+}
+
+/// The key resends to an email are limited under in Redis.
+#[inline]
+fn resend_key(email: &str) -> String {
+    format!("verify-email:resend:{}", email.to_lowercase())
 }
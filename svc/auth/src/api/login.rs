@@ -0,0 +1,180 @@
+//! Login and logout for users of the frontend.
+//!
+//! A successful login starts a [`Session`], after which the user is sent back
+//! to the authorization request that required them to login.
+//!
+//! Both endpoints only accept requests sent from the frontend or the service
+//! itself, going by the `Origin` or `Referer` header, so other sites can't log
+//! users in or out behind their backs.
+
+use crate::{config::CONFIG, state::AppState};
+
+use super::{
+    frontend_url,
+    oauth::password::{Verified, verify_user},
+    overloaded,
+    session::Session,
+};
+
+use lerpz_utils::axum::error::{HandlerError, HandlerResult};
+
+use axum::{
+    Form,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::Redirect,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use url::Url;
+
+/// The credentials posted by the login page of the frontend.
+#[derive(Deserialize, Debug)]
+pub struct LoginRequest {
+    /// Either the username or the email of the user.
+    login: String,
+    password: String,
+    /// The authorization request to return to after logging in.
+    return_to: Option<String>,
+}
+
+pub async fn handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Form(req): Form<LoginRequest>,
+) -> HandlerResult<(CookieJar, Redirect)> {
+    check_origin(&headers)?;
+
+    let return_to = req
+        .return_to
+        .as_deref()
+        .filter(|url| is_authorize_url(url, &CONFIG.ISSUER));
+
+    let error = match verify_user(&state, &req.login, &req.password).await? {
+        Verified::User(user) => {
+            // A new session id is handed out on every login, so an id planted
+            // in the browser beforehand is never logged in.
+            let jar = Session::destroy(&state.redis, jar).await?;
+            let jar = Session::create(&state.redis, jar, user.id).await?;
+            let to = return_to.unwrap_or(CONFIG.FRONTEND_URL.as_str());
+            return Ok((jar, Redirect::to(to)));
+        }
+        Verified::Invalid => "invalid_credentials",
+        Verified::Throttled => "throttled",
+        Verified::Unverified => "email_unverified",
+        Verified::Overloaded => return Err(overloaded()),
+    };
+
+    let mut params = vec![("error", error)];
+    params.extend(return_to.map(|url| ("return_to", url)));
+
+    let url = frontend_url("login", &params);
+    Ok((jar, Redirect::to(url.as_str())))
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+) -> HandlerResult<(CookieJar, Redirect)> {
+    check_origin(&headers)?;
+
+    let jar = Session::destroy(&state.redis, jar).await?;
+    Ok((jar, Redirect::to(CONFIG.FRONTEND_URL.as_str())))
+}
+
+/// Rejects requests that weren't sent from the frontend or the service.
+///
+/// The `Origin` header is used if the browser sent one, otherwise the
+/// `Referer`. Requests with neither are rejected as well.
+fn check_origin(headers: &HeaderMap) -> HandlerResult<()> {
+    let origin = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+        .and_then(|value| value.to_str().ok());
+
+    match origin {
+        Some(origin) if is_trusted_origin(origin, &CONFIG.FRONTEND_URL, &CONFIG.ISSUER) => Ok(()),
+        _ => Err(HandlerError::new(
+            StatusCode::FORBIDDEN,
+            "Forbidden",
+            "The request wasn't sent from a trusted origin.",
+        )),
+    }
+}
+
+/// Whether `origin` has the same origin as the frontend or the issuer.
+fn is_trusted_origin(origin: &str, frontend: &Url, issuer: &str) -> bool {
+    let Ok(origin) = Url::parse(origin) else {
+        return false;
+    };
+
+    origin.origin() == frontend.origin()
+        || Url::parse(issuer).is_ok_and(|issuer| origin.origin() == issuer.origin())
+}
+
+/// Whether `url` points to the authorization endpoint of `issuer`.
+///
+/// Only those are returned to after logging in, so that the login page can't
+/// be used to redirect users to other sites.
+fn is_authorize_url(url: &str, issuer: &str) -> bool {
+    let (Ok(url), Ok(issuer)) = (Url::parse(url), Url::parse(issuer)) else {
+        return false;
+    };
+
+    url.origin() == issuer.origin() && url.path() == "/api/oauth/authorize"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_authorize_url() {
+        let issuer = "https://auth.lerpz.local";
+
+        assert!(is_authorize_url(
+            "https://auth.lerpz.local/api/oauth/authorize?response_type=code",
+            issuer
+        ));
+        assert!(!is_authorize_url(
+            "https://evil.example/api/oauth/authorize",
+            issuer
+        ));
+        assert!(!is_authorize_url(
+            "https://auth.lerpz.local.evil.example/api/oauth/authorize",
+            issuer
+        ));
+        assert!(!is_authorize_url(
+            "https://auth.lerpz.local/api/logout",
+            issuer
+        ));
+        assert!(!is_authorize_url("/api/oauth/authorize", issuer));
+    }
+
+    #[test]
+    fn test_is_trusted_origin() {
+        let frontend = Url::parse("https://lerpz.local/app/").unwrap();
+        let issuer = "https://auth.lerpz.local";
+
+        assert!(is_trusted_origin("https://lerpz.local", &frontend, issuer));
+        assert!(is_trusted_origin(
+            "https://lerpz.local/app/login?error=throttled",
+            &frontend,
+            issuer
+        ));
+        assert!(is_trusted_origin(
+            "https://auth.lerpz.local",
+            &frontend,
+            issuer
+        ));
+        assert!(!is_trusted_origin("http://lerpz.local", &frontend, issuer));
+        assert!(!is_trusted_origin(
+            "https://evil.example",
+            &frontend,
+            issuer
+        ));
+        assert!(!is_trusted_origin("null", &frontend, issuer));
+    }
+}
@@ -7,29 +7,83 @@
 //! New User Journey:
 //! 1. POST /register → Create account
 //! 2. GET /verify-email → Verify email
+//!    (POST /verify-email/resend → Get a new verification link)
 //!
 //! Existing User Journey:
-//! 1. GET /oauth/authorize → Login & authorize app  
-//! 2. POST /oauth/token → Get access token
+//! 1. GET /oauth/authorize → Redirected to the frontend to login
+//! 2. POST /login → Start a session & return to /oauth/authorize
+//! 3. POST /oauth/consent → Authorize app, unless already authorized
+//! 4. POST /oauth/token → Get access token
 //!
 //! Password Recovery:
 //! 1. POST /forgot-password → Request reset
 //! 2. POST /reset-password → Set new password
 
 mod email_verify;
+mod login;
 mod oauth;
 mod pwd_forgot;
 mod pwd_reset;
 mod register;
+mod session;
 
-use crate::AppState;
+pub mod well_known;
+
+pub use oauth::bearer_auth;
+
+use crate::{AppState, config::CONFIG};
+
+use axum::http::{HeaderValue, StatusCode, header};
+use lerpz_utils::{axum::error::HandlerError, pwd};
+use url::Url;
+
+/// How long clients are asked to wait when too many passwords are being
+/// hashed, in seconds.
+const OVERLOADED_RETRY_AFTER: u64 = 1;
 
 pub fn router(state: AppState) -> axum::Router {
     axum::Router::<AppState>::new()
         .nest("/oauth", oauth::router(state.clone()))
+        .route("/login", axum::routing::post(login::handler))
+        .route("/logout", axum::routing::post(login::logout))
         .route("/register", axum::routing::post(register::handler))
         .route("/verify-email", axum::routing::get(email_verify::handler))
+        .route(
+            "/verify-email/resend",
+            axum::routing::post(email_verify::resend),
+        )
         .route("/forgot-password", axum::routing::post(pwd_forgot::handler))
         .route("/reset-password", axum::routing::post(pwd_reset::handler))
         .with_state(state)
 }
+
+/// Builds a URL to a page of the frontend.
+fn frontend_url(path: &str, params: &[(&str, &str)]) -> Url {
+    let mut url = CONFIG.FRONTEND_URL.join(path).expect("valid frontend path");
+    if !params.is_empty() {
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    url
+}
+
+/// The error returned when too many passwords are being hashed.
+fn overloaded() -> HandlerError {
+    HandlerError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Service unavailable",
+        "The server is busy, try again later.",
+    )
+    .with_header(
+        header::RETRY_AFTER,
+        HeaderValue::from(OVERLOADED_RETRY_AFTER),
+    )
+}
+
+/// Turns a password error into a [`HandlerError`], answering with
+/// [`overloaded`] when too many passwords are being hashed.
+fn pwd_error(err: pwd::Error) -> HandlerError {
+    match err {
+        pwd::Error::Overloaded => overloaded(),
+        err => err.into(),
+    }
+}
@@ -0,0 +1,142 @@
+//! Access tokens issued by the token endpoint.
+//!
+//! Access tokens are signed JWTs following the profile described in
+//! [RFC 9068](https://datatracker.ietf.org/doc/html/rfc9068). A token can be
+//! revoked before it expires by adding its `jti` to a denylist in Redis.
+
+use std::sync::{Arc, LazyLock};
+
+use crate::config::CONFIG;
+
+use super::keys::KEYS;
+
+use chrono::Utc;
+use lerpz_utils::{
+    axum::middelware::bearer::{BearerAuth, BoxFuture, Denylist, Scoped},
+    jwt::{self, Verifier},
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How long an access token is valid for, in seconds.
+pub const ACCESS_TOKEN_TTL: i64 = 60 * 15;
+
+/// The `typ` header of access tokens, which sets them apart from ID tokens
+/// signed with the same keys.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc9068#section-2.1
+const TOKEN_TYPE: &str = "at+jwt";
+
+/// The claims of an access token.
+pub type Claims = jwt::Claims<AccessTokenClaims>;
+
+/// Claims specific to access tokens.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc9068#section-2.2
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AccessTokenClaims {
+    /// The client the token was issued to.
+    pub client_id: String,
+    /// Space-delimited list of scopes granted to the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl Scoped for AccessTokenClaims {
+    fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+}
+
+/// How access tokens issued by this server are verified.
+///
+/// The audience is the client the token was issued to, which this server
+/// accepts tokens from regardless. Requiring the `typ` header keeps ID tokens
+/// from being accepted as access tokens.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc9068#section-4
+static VERIFIER: LazyLock<Verifier> = LazyLock::new(|| {
+    Verifier::new()
+        .with_issuer(&CONFIG.ISSUER)
+        .with_type(TOKEN_TYPE)
+        .with_any_audience()
+        .with_required_claims(&["exp", "iss", "sub"])
+});
+
+/// Signs a new access token.
+///
+/// The `jti` has to be unique, since it is used for revoking the token.
+pub fn issue(
+    jti: &str,
+    sub: impl Into<String>,
+    client_id: Uuid,
+    scope: Option<&str>,
+) -> jwt::Result<String> {
+    let now = Utc::now().timestamp();
+
+    let claims = Claims {
+        aud: client_id.to_string().into(),
+        iss: CONFIG.ISSUER.clone(),
+        sub: sub.into(),
+        exp: now + ACCESS_TOKEN_TTL,
+        nbf: now,
+        iat: now,
+        jti: Some(jti.into()),
+        extra: AccessTokenClaims {
+            client_id: client_id.to_string(),
+            scope: scope.map(Into::into),
+        },
+    };
+
+    KEYS.encode_typed(TOKEN_TYPE, &claims)
+}
+
+/// Verifies an access token issued by this server and returns its claims.
+///
+/// This does not check whether the token has been revoked.
+pub fn decode(token: &str) -> jwt::Result<Claims> {
+    VERIFIER.verify(&KEYS, token).map(|data| data.claims)
+}
+
+/// Authenticates requests using access tokens issued by this server.
+pub fn bearer_auth(redis: Arc<redis::Client>) -> BearerAuth {
+    BearerAuth::new(KEYS.clone(), VERIFIER.clone()).with_denylist(RedisDenylist(redis))
+}
+
+/// Revokes an access token by adding its `jti` to the denylist.
+///
+/// The entry is kept until the token would have expired anyway.
+pub async fn revoke(redis: &redis::Client, jti: &str, exp: i64) -> anyhow::Result<()> {
+    let ttl = exp - Utc::now().timestamp();
+    if ttl <= 0 {
+        return Ok(());
+    }
+
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let _: () = conn.set_ex(denylist_key(jti), 1, ttl as u64).await?;
+
+    Ok(())
+}
+
+/// Whether an access token has been revoked.
+pub async fn is_revoked(redis: &redis::Client, jti: &str) -> anyhow::Result<bool> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let revoked: bool = conn.exists(denylist_key(jti)).await?;
+    Ok(revoked)
+}
+
+/// The denylist of revoked access tokens.
+struct RedisDenylist(Arc<redis::Client>);
+
+impl Denylist for RedisDenylist {
+    fn is_revoked<'a>(&'a self, jti: &'a str) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(is_revoked(&self.0, jti))
+    }
+}
+
+/// The key a revoked access token is stored under in Redis.
+#[inline]
+fn denylist_key(jti: &str) -> String {
+    format!("oauth:revoked:{jti}")
+}
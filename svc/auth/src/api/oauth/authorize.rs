@@ -1,26 +1,178 @@
-use lerpz_utils::axum::error::{HandlerError, HandlerResult};
+use crate::{
+    api::{frontend_url, session::Session},
+    config::CONFIG,
+    state::AppState,
+};
 
-use axum::{http::StatusCode, response::Redirect, Form};
+use super::{
+    access_token::{self, ACCESS_TOKEN_TTL},
+    code::AuthorizationCode,
+    consent::{self, PendingConsent},
+    id_token::{self, NewIdToken},
+    pkce::CodeChallenge,
+    scope,
+};
+
+use lerpz_core::db::OAuthClient;
+use lerpz_utils::{
+    axum::error::{HandlerError, HandlerResult},
+    html::escape_html,
+};
+
+use axum::{
+    Form,
+    extract::{OriginalUri, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
-use serde_urlencoded::to_string;
-use url::Url;
+use url::{Url, form_urlencoded};
+use uuid::Uuid;
 
 /// Represents an OAuth 2.0 request to the authorization endpoint.
+///
+/// Source: https://openid.net/specs/oauth-v2-multiple-response-types-1_0.html#Combinations
 #[derive(Deserialize, Debug)]
 #[serde(tag = "response_type")]
-#[serde(rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum AuthorizationRequest {
+    #[serde(rename = "code")]
     AuthorizationCode(AuthorizationCodeRequest),
+    #[serde(rename = "token")]
+    Token(ImplicitGrantRequest),
+    #[serde(rename = "id_token")]
+    IdToken(ImplicitGrantRequest),
+    #[serde(rename = "code id_token", alias = "id_token code")]
+    CodeIdToken(AuthorizationCodeRequest),
+}
+
+impl AuthorizationRequest {
+    fn response_type(&self) -> ResponseType {
+        match self {
+            Self::AuthorizationCode(_) => ResponseType::Code,
+            Self::Token(_) => ResponseType::Token,
+            Self::IdToken(_) => ResponseType::IdToken,
+            Self::CodeIdToken(_) => ResponseType::CodeIdToken,
+        }
+    }
+
+    fn params(&self) -> AuthorizationParams<'_> {
+        match self {
+            Self::AuthorizationCode(req) | Self::CodeIdToken(req) => AuthorizationParams {
+                client_id: &req.client_id,
+                redirect_uri: &req.redirect_uri,
+                scope: req.scope.as_deref(),
+                state: req.state.as_deref(),
+                nonce: req.nonce.as_deref(),
+                response_mode: req.response_mode,
+                code_challenge: req.code_challenge.as_deref(),
+                code_challenge_method: req.code_challenge_method.as_deref(),
+            },
+            Self::Token(req) | Self::IdToken(req) => AuthorizationParams {
+                client_id: &req.client_id,
+                redirect_uri: &req.redirect_uri,
+                scope: req.scope.as_deref(),
+                state: req.state.as_deref(),
+                nonce: req.nonce.as_deref(),
+                response_mode: req.response_mode,
+                code_challenge: None,
+                code_challenge_method: None,
+            },
+        }
+    }
+}
+
+/// The parameters of an authorization request, regardless of response type.
+#[derive(Debug, Clone, Copy)]
+struct AuthorizationParams<'a> {
+    client_id: &'a str,
+    redirect_uri: &'a str,
+    scope: Option<&'a str>,
+    state: Option<&'a str>,
+    nonce: Option<&'a str>,
+    response_mode: Option<ResponseMode>,
+    code_challenge: Option<&'a str>,
+    code_challenge_method: Option<&'a str>,
+}
+
+/// What the authorization endpoint returns to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseType {
+    Code,
+    Token,
+    IdToken,
+    CodeIdToken,
+}
+
+impl ResponseType {
+    /// The response type as registered for clients.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::Token => "token",
+            Self::IdToken => "id_token",
+            Self::CodeIdToken => "code id_token",
+        }
+    }
+
+    fn has_code(self) -> bool {
+        matches!(self, Self::Code | Self::CodeIdToken)
+    }
+
+    fn has_id_token(self) -> bool {
+        matches!(self, Self::IdToken | Self::CodeIdToken)
+    }
+
+    /// The response mode used when the client doesn't ask for one.
+    ///
+    /// Source: https://openid.net/specs/oauth-v2-multiple-response-types-1_0.html#ResponseModes
+    fn default_mode(self) -> ResponseMode {
+        match self {
+            Self::Code => ResponseMode::Query,
+            _ => ResponseMode::Fragment,
+        }
+    }
+}
+
+/// How the response is delivered to the redirect URI.
+///
+/// Source: https://openid.net/specs/oauth-v2-multiple-response-types-1_0.html#ResponseModes
+/// Source: https://openid.net/specs/oauth-v2-form-post-response-mode-1_0.html
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseMode {
+    Query,
+    Fragment,
+    FormPost,
 }
 
 /// Represents an OAuth 2.0 response from the authorization endpoint.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
 pub enum AuthorizationResponse {
     AuthorizationCode(AuthorizationCodeResponse),
+    Implicit(ImplicitGrantResponse),
+}
+
+impl AuthorizationResponse {
+    /// Creates a failed response with a description of the error.
+    ///
+    /// Errors look the same for every response type.
+    pub(super) fn failed(
+        error: AuthorizationErrorKind,
+        description: impl Into<String>,
+        state: Option<String>,
+    ) -> Self {
+        Self::AuthorizationCode(AuthorizationCodeResponse::failed(error, description, state))
+    }
 }
 
 /// A request to initiate the OAuth 2.0 authorization code flow.
 ///
+/// This is also used for the hybrid flow, where an ID token is returned
+/// together with the code.
+///
 /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.1
 #[derive(Deserialize, Debug)]
 pub struct AuthorizationCodeRequest {
@@ -28,12 +180,24 @@ pub struct AuthorizationCodeRequest {
     redirect_uri: String,
     scope: Option<String>,
     state: Option<String>,
+    /// The PKCE code challenge.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.3
+    code_challenge: Option<String>,
+    /// Either `plain` or `S256`. Defaults to `plain` if missing.
+    code_challenge_method: Option<String>,
+    /// Passed through unmodified to the ID token.
+    ///
+    /// Source: https://openid.net/specs/openid-connect-core-1_0.html#AuthRequest
+    nonce: Option<String>,
+    response_mode: Option<ResponseMode>,
 }
 
 /// A response to an authorization code request.
 ///
 /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2
 #[derive(Serialize, Debug)]
+#[serde(untagged)]
 pub enum AuthorizationCodeResponse {
     Success {
         code: Option<String>,
@@ -47,38 +211,57 @@ pub enum AuthorizationCodeResponse {
     },
 }
 
-/// A request to initiate the OAuth 2.0 implicit grant flow
+impl AuthorizationCodeResponse {
+    /// Creates a failed response with a description of the error.
+    fn failed(
+        error: AuthorizationErrorKind,
+        description: impl Into<String>,
+        state: Option<String>,
+    ) -> Self {
+        Self::Failed {
+            error,
+            error_description: Some(description.into()),
+            error_uri: None,
+            state,
+        }
+    }
+}
+
+/// A request to initiate the OAuth 2.0 implicit grant flow.
 ///
 /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.2.1
 #[derive(Deserialize, Debug)]
 pub struct ImplicitGrantRequest {
     client_id: String,
-    redirect_uri: Option<String>,
+    redirect_uri: String,
     scope: Option<String>,
     state: Option<String>,
+    /// Required when an ID token is requested.
+    ///
+    /// Source: https://openid.net/specs/openid-connect-core-1_0.html#ImplicitAuthRequest
+    nonce: Option<String>,
+    response_mode: Option<ResponseMode>,
 }
 
-/// A response to an authorization request in the implicit grant flow.
+/// A successful response in the implicit or hybrid flow.
 ///
 /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.2.2
-#[derive(Serialize, Debug)]
-pub enum ImplicitGratResponse {
-    Success {
-        access_token: String,
-        token_type: String,
-        expires_in: Option<u64>,
-        scope: Option<String>,
-        state: Option<String>,
-    },
-    Failed {
-        error: AuthorizationErrorKind,
-        error_description: Option<String>,
-        error_uri: Option<String>,
-        state: Option<String>,
-    },
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#HybridAuthResponse
+#[derive(Serialize, Debug, Default)]
+pub struct ImplicitGrantResponse {
+    code: Option<String>,
+    access_token: Option<String>,
+    token_type: Option<String>,
+    expires_in: Option<u64>,
+    id_token: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
 }
 
-/// Possible errors that can occur during the implicit grant flow.
+/// The errors the authorization endpoint redirects back to the client.
+///
+/// Unknown response types are rejected before the client is known, so
+/// `unsupported_response_type` is never redirected.
 ///
 /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.2.2.1
 #[derive(Serialize, Debug)]
@@ -87,49 +270,344 @@ pub enum AuthorizationErrorKind {
     InvalidRequest,
     UnauthorizedClient,
     AccessDenied,
-    UnsupportedResponseType,
     InvalidScope,
     ServerError,
-    TemporarilyUnavailable,
 }
 
 #[axum::debug_handler]
-pub async fn handler(Form(query): Form<AuthorizationRequest>) -> HandlerResult<Redirect> {
-    let AuthorizationRequest::AuthorizationCode(req) = query;
-    let res = authorization_code(&req)?;
+pub async fn handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    OriginalUri(uri): OriginalUri,
+    Form(query): Form<AuthorizationRequest>,
+) -> HandlerResult<Response> {
+    let response_type = query.response_type();
+    let req = query.params();
+
+    // Errors are only redirected back to the client once both the client and
+    // the redirect URI are known to be valid.
+    //
+    // Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2.1
+    let client = find_client(&state, req.client_id, req.redirect_uri).await?;
+
+    // Tokens are never put in the query, where they are more likely to leak
+    // through logs and the referer header.
+    //
+    // Source: https://openid.net/specs/oauth-v2-multiple-response-types-1_0.html#ResponseModes
+    let mode = match req.response_mode {
+        Some(ResponseMode::Query) if response_type != ResponseType::Code => {
+            let res = AuthorizationResponse::failed(
+                AuthorizationErrorKind::InvalidRequest,
+                "The query response mode can't be used with this response type.",
+                req.state.map(Into::into),
+            );
+            return respond(req.redirect_uri, response_type.default_mode(), &res);
+        }
+        Some(mode) => mode,
+        None => response_type.default_mode(),
+    };
+
+    let (code_challenge, scope) = match validate(&client, &req, response_type) {
+        Ok(validated) => validated,
+        Err(res) => return respond(req.redirect_uri, mode, &res),
+    };
+
+    // The login and consent pages of the frontend return to this exact
+    // request once the user is done with them.
+    let return_to = format!("{}{}", CONFIG.ISSUER, uri);
+
+    let Some(session) = Session::find(&state.redis, &jar).await? else {
+        let url = frontend_url("login", &[("return_to", &return_to)]);
+        return Ok(Redirect::to(url.as_str()).into_response());
+    };
+
+    if !consent::is_granted(&state.database, session.user_id, client.id, &scope).await? {
+        let consent_id = PendingConsent {
+            user_id: session.user_id,
+            client_id: client.id,
+            scope: scope.clone(),
+            return_to,
+            redirect_uri: req.redirect_uri.into(),
+            response_mode: mode,
+            state: req.state.map(Into::into),
+        }
+        .store(&state.redis)
+        .await?;
+
+        let url = frontend_url(
+            "consent",
+            &[
+                ("consent_id", &consent_id),
+                ("client_id", &client.id.to_string()),
+                ("client_name", &client.name),
+                ("scope", &scope),
+            ],
+        );
+        return Ok(Redirect::to(url.as_str()).into_response());
+    }
 
-    let redirect_uri = extend_url_query(&req.redirect_uri, &res)?;
+    let code = response_type.has_code().then(|| AuthorizationCode {
+        client_id: client.id,
+        user_id: session.user_id,
+        redirect_uri: req.redirect_uri.into(),
+        scope: Some(scope.clone()).filter(|s| !s.is_empty()),
+        code_challenge,
+        nonce: req.nonce.map(Into::into),
+        auth_time: session.auth_time,
+    });
 
-    Ok(Redirect::to(redirect_uri.as_str()))
+    let res = match code {
+        Some(code) if response_type == ResponseType::Code => {
+            AuthorizationResponse::AuthorizationCode(
+                authorization_code(&state, code, req.state.map(Into::into)).await,
+            )
+        }
+        code => {
+            implicit_grant(
+                &state,
+                ImplicitGrant {
+                    response_type,
+                    client_id: client.id,
+                    user_id: session.user_id,
+                    auth_time: session.auth_time,
+                    code,
+                    scope,
+                    req,
+                },
+            )
+            .await
+        }
+    };
+
+    respond(req.redirect_uri, mode, &res)
 }
 
-fn authorization_code(req: &AuthorizationCodeRequest) -> HandlerResult<AuthorizationCodeResponse> {
-    Ok(AuthorizationCodeResponse::Success {
-        code: Some("generated_code".to_string()),
-        state: req.state.clone(),
-    })
+/// Validates the parts of the request that don't depend on the user.
+///
+/// Returns the PKCE challenge and the scope the user is asked to grant, which
+/// defaults to every scope registered for the client.
+fn validate(
+    client: &OAuthClient,
+    req: &AuthorizationParams<'_>,
+    response_type: ResponseType,
+) -> Result<(Option<CodeChallenge>, String), AuthorizationResponse> {
+    let failed = |error, description| {
+        AuthorizationResponse::failed(error, description, req.state.map(Into::into))
+    };
+
+    if !client.allows_response_type(response_type.as_str()) {
+        return Err(failed(
+            AuthorizationErrorKind::UnauthorizedClient,
+            "The client may not use this response type.",
+        ));
+    }
+
+    let code_challenge = match req.code_challenge {
+        Some(challenge) => match CodeChallenge::new(challenge, req.code_challenge_method) {
+            Ok(challenge) => Some(challenge),
+            Err(description) => {
+                return Err(failed(AuthorizationErrorKind::InvalidRequest, description));
+            }
+        },
+        None if response_type.has_code() && client.requires_pkce() => {
+            return Err(failed(
+                AuthorizationErrorKind::InvalidRequest,
+                "A code challenge is required for this client.",
+            ));
+        }
+        None => None,
+    };
+
+    let registered = client.scopes.join(" ");
+    let scope = match req.scope {
+        Some(requested) if !scope::is_subset(requested, &registered) => {
+            return Err(failed(
+                AuthorizationErrorKind::InvalidScope,
+                "The requested scope exceeds the scope registered for the client.",
+            ));
+        }
+        Some(requested) => requested.to_string(),
+        None => registered,
+    };
+
+    // Source: https://openid.net/specs/openid-connect-core-1_0.html#ImplicitAuthRequest
+    if response_type.has_id_token() {
+        if !scope::split(&scope).any(|s| s == "openid") {
+            return Err(failed(
+                AuthorizationErrorKind::InvalidScope,
+                "The openid scope is required for an ID token.",
+            ));
+        }
+        if req.nonce.is_none() {
+            return Err(failed(
+                AuthorizationErrorKind::InvalidRequest,
+                "A nonce is required for an ID token.",
+            ));
+        }
+    }
+
+    Ok((code_challenge, scope))
+}
+
+async fn authorization_code(
+    state: &AppState,
+    code: AuthorizationCode,
+    client_state: Option<String>,
+) -> AuthorizationCodeResponse {
+    match code.store(&state.redis).await {
+        Ok(code) => AuthorizationCodeResponse::Success {
+            code: Some(code),
+            state: client_state,
+        },
+        Err(err) => {
+            tracing::error!(error = %err, "failed storing authorization code");
+            AuthorizationCodeResponse::failed(
+                AuthorizationErrorKind::ServerError,
+                "Couldn't issue an authorization code.",
+                client_state,
+            )
+        }
+    }
 }
 
-fn implicit_grant(req: ImplicitGrantRequest) -> HandlerResult<ImplicitGratResponse> {
-    Ok(ImplicitGratResponse::Success {
-        access_token: "generated_access_token".to_string(),
-        token_type: "Bearer".to_string(),
-        expires_in: Some(3600),
-        scope: req.scope,
-        state: req.state,
+/// Finds the client and makes sure the redirect URI is registered for it.
+///
+/// The redirect URI has to match one of the registered URIs exactly.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-3.1.2.3
+async fn find_client(
+    state: &AppState,
+    client_id: &str,
+    redirect_uri: &str,
+) -> HandlerResult<OAuthClient> {
+    let invalid_client = || {
+        HandlerError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid client",
+            "The client is unknown or the redirect URI isn't registered for it.",
+        )
+    };
+
+    let client_id = Uuid::parse_str(client_id).map_err(|_| invalid_client())?;
+
+    let client = sqlx::query_as!(
+        OAuthClient,
+        "SELECT id, secret_hash, secret_salt, name, description, organization_id,
+        require_pkce, scopes, grant_types, response_types
+        FROM oauth_clients
+        WHERE id = $1",
+        client_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or_else(invalid_client)?;
+
+    let registered = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+        SELECT 1 FROM redirect_uris WHERE client_id = $1 AND uri = $2
+        ) AS "exists!""#,
+        client.id,
+        redirect_uri
+    )
+    .fetch_one(&state.database)
+    .await?;
+
+    if !registered {
+        return Err(invalid_client());
+    }
+
+    Ok(client)
+}
+
+/// Everything needed to respond to an implicit or hybrid request.
+struct ImplicitGrant<'a> {
+    response_type: ResponseType,
+    client_id: Uuid,
+    user_id: Uuid,
+    auth_time: i64,
+    /// The authorization code to issue in the hybrid flow.
+    code: Option<AuthorizationCode>,
+    scope: String,
+    req: AuthorizationParams<'a>,
+}
+
+/// Issues the tokens of the implicit and hybrid flows.
+///
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#ImplicitAuthResponse
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#HybridAuthResponse
+async fn implicit_grant(state: &AppState, grant: ImplicitGrant<'_>) -> AuthorizationResponse {
+    let client_state = grant.req.state.map(Into::into);
+    let server_error = |err: &dyn std::fmt::Display| {
+        tracing::error!(error = %err, "failed issuing tokens from the authorization endpoint");
+        AuthorizationResponse::failed(
+            AuthorizationErrorKind::ServerError,
+            "Couldn't issue the requested tokens.",
+            client_state.clone(),
+        )
+    };
+
+    let code = match grant.code {
+        Some(code) => match code.store(&state.redis).await {
+            Ok(code) => Some(code),
+            Err(err) => return server_error(&err),
+        },
+        None => None,
+    };
+
+    let scope = Some(grant.scope.as_str()).filter(|s| !s.is_empty());
+    let access_token = match grant.response_type {
+        ResponseType::Token => {
+            let jti = Uuid::new_v4().to_string();
+            match access_token::issue(&jti, grant.user_id.to_string(), grant.client_id, scope) {
+                Ok(token) => Some(token),
+                Err(err) => return server_error(&err),
+            }
+        }
+        _ => None,
+    };
+
+    let id_token = if grant.response_type.has_id_token() {
+        let new = NewIdToken {
+            user_id: grant.user_id,
+            client_id: grant.client_id,
+            nonce: grant.req.nonce,
+            auth_time: grant.auth_time,
+            access_token: access_token.as_deref(),
+            code: code.as_deref(),
+        };
+        match id_token::issue(new) {
+            Ok(token) => Some(token),
+            Err(err) => return server_error(&err),
+        }
+    } else {
+        None
+    };
+
+    AuthorizationResponse::Implicit(ImplicitGrantResponse {
+        token_type: access_token.as_ref().map(|_| "Bearer".into()),
+        expires_in: access_token.as_ref().map(|_| ACCESS_TOKEN_TTL as u64),
+        scope: grant.req.scope.is_none().then_some(grant.scope),
+        state: client_state.clone(),
+        code,
+        access_token,
+        id_token,
     })
 }
 
-fn extend_url_query<T: Serialize>(url_str: &str, params: &T) -> Result<Url, HandlerError> {
-    let mut url = Url::parse(url_str)
-        .map_err(|_| HandlerError::new(
+pub(super) fn extend_url_query<T: Serialize>(
+    url_str: &str,
+    params: &T,
+) -> Result<Url, HandlerError> {
+    let mut url = Url::parse(url_str).map_err(|_| {
+        HandlerError::new(
             StatusCode::BAD_REQUEST,
             "Invalid redirect URI",
-            "The redirect URI provided is not valid."
-        ))?;
-    
+            "The redirect URI provided is not valid.",
+        )
+    })?;
+
     let query_string = serde_urlencoded::to_string(params)?;
-    
+
     if !query_string.is_empty() {
         if url.query().is_some() {
             let existing_query = url.query().unwrap();
@@ -138,6 +616,118 @@ fn extend_url_query<T: Serialize>(url_str: &str, params: &T) -> Result<Url, Hand
             url.set_query(Some(&query_string));
         }
     }
-    
+
     Ok(url)
 }
+
+/// Sends the response back to the client using the given response mode.
+pub(super) fn respond<T: Serialize>(
+    redirect_uri: &str,
+    mode: ResponseMode,
+    res: &T,
+) -> HandlerResult<Response> {
+    match mode {
+        ResponseMode::Query => {
+            let url = extend_url_query(redirect_uri, res)?;
+            Ok(Redirect::to(url.as_str()).into_response())
+        }
+        ResponseMode::Fragment => {
+            let mut url = Url::parse(redirect_uri).map_err(|_| {
+                HandlerError::new(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid redirect URI",
+                    "The redirect URI provided is not valid.",
+                )
+            })?;
+            url.set_fragment(Some(&serde_urlencoded::to_string(res)?));
+            Ok(Redirect::to(url.as_str()).into_response())
+        }
+        ResponseMode::FormPost => {
+            let params = serde_urlencoded::to_string(res)?;
+            Ok(Html(form_post(redirect_uri, &params)).into_response())
+        }
+    }
+}
+
+/// Renders a page that posts `params` to the redirect URI as soon as it loads.
+///
+/// Source: https://openid.net/specs/oauth-v2-form-post-response-mode-1_0.html#FormPostResponseMode
+fn form_post(redirect_uri: &str, params: &str) -> String {
+    let inputs: String = form_urlencoded::parse(params.as_bytes())
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}"/>"#,
+                escape_html(&name),
+                escape_html(&value)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><title>Submit This Form</title></head>
+<body onload="document.forms[0].submit()">
+<form method="post" action="{}">{inputs}<noscript><button type="submit">Continue</button></noscript></form>
+</body>
+</html>"#,
+        escape_html(redirect_uri)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> AuthorizationRequest {
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    #[test]
+    fn test_response_type() {
+        let base = "client_id=a&redirect_uri=https%3A%2F%2Fexample.com";
+
+        let cases = [
+            ("code", ResponseType::Code),
+            ("token", ResponseType::Token),
+            ("id_token", ResponseType::IdToken),
+            ("code+id_token", ResponseType::CodeIdToken),
+            ("id_token+code", ResponseType::CodeIdToken),
+        ];
+        for (response_type, expected) in cases {
+            let req = parse(&format!("response_type={response_type}&{base}"));
+            assert_eq!(req.response_type(), expected);
+        }
+
+        let req = parse(&format!(
+            "response_type=token&response_mode=form_post&{base}"
+        ));
+        assert_eq!(req.params().response_mode, Some(ResponseMode::FormPost));
+        assert!(serde_urlencoded::from_str::<AuthorizationRequest>(base).is_err());
+    }
+
+    #[test]
+    fn test_respond_fragment() {
+        let res = AuthorizationResponse::Implicit(ImplicitGrantResponse {
+            access_token: Some("token".into()),
+            token_type: Some("Bearer".into()),
+            state: Some("xyz".into()),
+            ..Default::default()
+        });
+
+        let res = respond("https://example.com/cb?a=b", ResponseMode::Fragment, &res).unwrap();
+        let location = res.headers()["location"].to_str().unwrap();
+        assert_eq!(
+            location,
+            "https://example.com/cb?a=b#access_token=token&token_type=Bearer&state=xyz"
+        );
+    }
+
+    #[test]
+    fn test_form_post() {
+        let html = form_post("https://example.com/cb", "code=a%22b&state=%3Cx%3E");
+        assert!(html.contains(r#"action="https://example.com/cb""#));
+        assert!(html.contains(r#"name="code" value="a&quot;b""#));
+        assert!(html.contains(r#"name="state" value="&lt;x&gt;""#));
+    }
+}
@@ -0,0 +1,144 @@
+//! Authentication of OAuth clients.
+
+use crate::{api::overloaded, state::AppState};
+
+use super::token::error::{TokenError, TokenResult};
+
+use axum::http::{HeaderMap, header};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use lerpz_core::db::OAuthClient;
+use lerpz_utils::pwd;
+use percent_encoding::percent_decode_str;
+use uuid::Uuid;
+
+/// Authenticates a client using HTTP Basic (`client_secret_basic`) or the
+/// `client_id` and `client_secret` from the request body
+/// (`client_secret_post`).
+///
+/// Public clients don't have a secret, so they are only identified by their
+/// `client_id`. These clients are protected by PKCE instead.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-2.3.1
+pub async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> TokenResult<OAuthClient> {
+    let invalid_client = TokenError::invalid_client;
+
+    let (client_id, client_secret) = match basic_credentials(headers)? {
+        Some(_) if client_secret.is_some() => {
+            return Err(TokenError::invalid_request(
+                "The client can only use one authentication method.",
+            ));
+        }
+        Some((id, _)) if client_id.is_some_and(|client_id| client_id != id) => {
+            return Err(TokenError::invalid_request(
+                "The \"client_id\" does not match the authenticated client.",
+            ));
+        }
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            client_id.ok_or_else(invalid_client)?.to_string(),
+            client_secret.map(Into::into),
+        ),
+    };
+
+    let client_id = Uuid::parse_str(&client_id).map_err(|_| invalid_client())?;
+
+    let client = sqlx::query_as!(
+        OAuthClient,
+        "SELECT id, secret_hash, secret_salt, name, description, organization_id,
+        require_pkce, scopes, grant_types, response_types
+        FROM oauth_clients
+        WHERE id = $1",
+        client_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or_else(invalid_client)?;
+
+    if let Some(secret_hash) = client.secret_hash.as_deref() {
+        let client_secret = client_secret.ok_or_else(invalid_client)?;
+        let valid = match pwd::validate_pwd(
+            secret_hash,
+            client_secret,
+            client.secret_salt.as_deref(),
+        )
+        .await
+        {
+            Ok(validation) => validation.is_valid(),
+            Err(pwd::Error::Overloaded) => return Err(TokenError::Internal(overloaded())),
+            Err(err) => {
+                tracing::warn!(client_id = %client.id, "can't validate client secret: {err}");
+                false
+            }
+        };
+
+        if !valid {
+            return Err(invalid_client());
+        }
+    }
+
+    Ok(client)
+}
+
+/// Gets the client credentials from an `Authorization: Basic` header.
+///
+/// Both the id and the secret are form-urlencoded before being joined.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-2.3.1
+fn basic_credentials(headers: &HeaderMap) -> TokenResult<Option<(String, String)>> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let credentials = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Basic"))
+        .and_then(|(_, credentials)| BASE64_STANDARD.decode(credentials.trim()).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .ok_or_else(TokenError::invalid_client)?;
+
+    let (id, secret) = credentials
+        .split_once(':')
+        .ok_or_else(TokenError::invalid_client)?;
+
+    Ok(Some((form_decode(id)?, form_decode(secret)?)))
+}
+
+fn form_decode(value: &str) -> TokenResult<String> {
+    percent_decode_str(&value.replace('+', " "))
+        .decode_utf8()
+        .map(Into::into)
+        .map_err(|_| TokenError::invalid_client())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_basic_credentials() {
+        let mut headers = HeaderMap::new();
+        assert!(basic_credentials(&headers).unwrap().is_none());
+
+        // "client:se cr%et" encoded as "client:se+cr%25et".
+        let encoded = BASE64_STANDARD.encode("client:se+cr%25et");
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {encoded}")).unwrap(),
+        );
+        let (id, secret) = basic_credentials(&headers).unwrap().unwrap();
+        assert_eq!(id, "client");
+        assert_eq!(secret, "se cr%et");
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic !!"));
+        assert!(basic_credentials(&headers).is_err());
+    }
+}
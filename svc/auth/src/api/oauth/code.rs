@@ -0,0 +1,139 @@
+//! Authorization codes issued by the authorization endpoint.
+//!
+//! Codes are stored in Redis with a short TTL and can only be consumed once.
+
+use std::sync::LazyLock;
+
+use redis::{AsyncCommands, Script};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{generate_secret, pkce::CodeChallenge};
+
+/// How long an authorization code is valid for, in seconds.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2
+const AUTHORIZATION_CODE_TTL: u64 = 60 * 5;
+
+/// How long a consumed authorization code is remembered, in seconds.
+///
+/// Replays of the code are detected for this long, after which they are
+/// treated like any other unknown code.
+const USED_AUTHORIZATION_CODE_TTL: u64 = 60 * 60 * 24;
+
+/// Atomically consumes an authorization code.
+///
+/// The code is deleted and replaced by a marker describing what was issued for
+/// it, so that replays of the code can be detected until the marker expires.
+/// Returns `["fresh", <code>]`, `["replayed", <marker>]` or an empty array.
+static CONSUME_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local code = redis.call('GETDEL', KEYS[1])
+        if code then
+            redis.call('SET', KEYS[2], ARGV[1], 'EX', ARGV[2])
+            return {'fresh', code}
+        end
+        local used = redis.call('GET', KEYS[2])
+        if used then
+            return {'replayed', used}
+        end
+        return {}
+        ",
+    )
+});
+
+/// Everything an authorization code is bound to when it is issued.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthorizationCode {
+    /// The client the code was issued to.
+    pub client_id: Uuid,
+    /// The user that authorized the client.
+    pub user_id: Uuid,
+    /// The exact redirect URI used in the authorization request.
+    pub redirect_uri: String,
+    /// The scope that was requested by the client.
+    pub scope: Option<String>,
+    /// The PKCE challenge the code verifier has to match.
+    pub code_challenge: Option<CodeChallenge>,
+    /// The `nonce` to include in the ID token.
+    pub nonce: Option<String>,
+    /// When the user authenticated, as a unix timestamp.
+    pub auth_time: i64,
+}
+
+impl AuthorizationCode {
+    /// Stores the authorization code and returns the code given to the client.
+    pub async fn store(&self, redis: &redis::Client) -> anyhow::Result<String> {
+        let code = generate_secret();
+
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let value = serde_json::to_string(self)?;
+        let _: () = conn
+            .set_ex(redis_key(&code), value, AUTHORIZATION_CODE_TTL)
+            .await?;
+
+        Ok(code)
+    }
+}
+
+/// Records what was issued in exchange for an authorization code.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsedAuthorizationCode {
+    /// The ID of the access token issued for the code.
+    pub jti: String,
+    /// When the access token issued for the code expires.
+    pub exp: i64,
+    /// The family of the refresh token issued for the code.
+    pub family_id: Uuid,
+}
+
+/// The outcome of consuming an authorization code.
+#[derive(Debug)]
+pub enum Consumed {
+    /// The code was valid and has now been used.
+    Fresh(AuthorizationCode),
+    /// The code has already been used.
+    Replayed(UsedAuthorizationCode),
+    /// The code was never issued or has expired.
+    Unknown,
+}
+
+/// Consumes an authorization code so that it can't be used again.
+///
+/// The `used` marker should describe the tokens that will be issued if the
+/// code turns out to be [`Consumed::Fresh`].
+pub async fn consume(
+    redis: &redis::Client,
+    code: &str,
+    used: &UsedAuthorizationCode,
+) -> anyhow::Result<Consumed> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let res: Vec<String> = CONSUME_SCRIPT
+        .key(redis_key(code))
+        .key(used_redis_key(code))
+        .arg(serde_json::to_string(used)?)
+        .arg(USED_AUTHORIZATION_CODE_TTL)
+        .invoke_async(&mut conn)
+        .await?;
+
+    let consumed = match res.as_slice() {
+        [kind, value] if kind == "fresh" => Consumed::Fresh(serde_json::from_str(value)?),
+        [kind, value] if kind == "replayed" => Consumed::Replayed(serde_json::from_str(value)?),
+        _ => Consumed::Unknown,
+    };
+
+    Ok(consumed)
+}
+
+/// The key an authorization code is stored under in Redis.
+#[inline]
+fn redis_key(code: &str) -> String {
+    format!("oauth:code:{code}")
+}
+
+/// The key a consumed authorization code is remembered under in Redis.
+#[inline]
+fn used_redis_key(code: &str) -> String {
+    format!("oauth:code:used:{code}")
+}
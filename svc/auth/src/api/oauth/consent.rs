@@ -0,0 +1,176 @@
+//! Consent of users to share scopes with clients.
+//!
+//! When a client requests scopes the user hasn't agreed to share with it yet,
+//! the request is parked in Redis while the frontend asks the user for their
+//! consent. The consent is remembered, so that the user is only asked again
+//! when the client requests more scopes.
+//!
+//! Source: https://openid.net/specs/openid-connect-core-1_0.html#Consent
+
+use crate::{api::session::Session, state::AppState};
+
+use super::{
+    authorize::{AuthorizationErrorKind, AuthorizationResponse, ResponseMode, respond},
+    generate_secret, scope,
+};
+
+use lerpz_core::db::Consent;
+use lerpz_utils::axum::error::{HandlerError, HandlerResult};
+
+use axum::{
+    Form,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// How long the user has to answer a consent request, in seconds.
+const PENDING_CONSENT_TTL: u64 = 60 * 10;
+
+/// An authorization request waiting for the user's consent.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingConsent {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    /// The scopes the user is asked to share.
+    pub scope: String,
+    /// The authorization request to return to once the user has consented.
+    pub return_to: String,
+    /// Where to send the user if they deny the request.
+    pub redirect_uri: String,
+    pub response_mode: ResponseMode,
+    pub state: Option<String>,
+}
+
+impl PendingConsent {
+    /// Stores the consent request and returns the ID given to the frontend.
+    pub async fn store(&self, redis: &redis::Client) -> anyhow::Result<String> {
+        let id = generate_secret();
+
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let value = serde_json::to_string(self)?;
+        let _: () = conn
+            .set_ex(redis_key(&id), value, PENDING_CONSENT_TTL)
+            .await?;
+
+        Ok(id)
+    }
+
+    /// Removes and returns the consent request with the given ID.
+    pub async fn take(redis: &redis::Client, id: &str) -> anyhow::Result<Option<Self>> {
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let value: Option<String> = conn.get_del(redis_key(id)).await?;
+
+        Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+    }
+}
+
+/// Whether the user has already agreed to share `scope` with the client.
+pub async fn is_granted(
+    db: &PgPool,
+    user_id: Uuid,
+    client_id: Uuid,
+    scope: &str,
+) -> sqlx::Result<bool> {
+    let consent = sqlx::query_as!(
+        Consent,
+        "SELECT user_id, client_id, scopes, created_at, updated_at
+        FROM consents
+        WHERE user_id = $1 AND client_id = $2",
+        user_id,
+        client_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(consent.is_some_and(|c| c.covers(scope::split(scope))))
+}
+
+/// Remembers that the user agreed to share `scope` with the client, in
+/// addition to the scopes they already agreed to.
+async fn grant(db: &PgPool, user_id: Uuid, client_id: Uuid, scope: &str) -> sqlx::Result<()> {
+    let scopes: Vec<String> = scope::split(scope).map(String::from).collect();
+
+    sqlx::query!(
+        "INSERT INTO consents (user_id, client_id, scopes)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, client_id) DO UPDATE SET
+        scopes = ARRAY(SELECT DISTINCT unnest(consents.scopes || EXCLUDED.scopes))",
+        user_id,
+        client_id,
+        &scopes
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// The answer of the user to a consent request.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+/// The form posted by the consent page of the frontend.
+#[derive(Deserialize, Debug)]
+pub struct ConsentRequest {
+    consent_id: String,
+    decision: Decision,
+}
+
+pub async fn handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Form(req): Form<ConsentRequest>,
+) -> HandlerResult<Response> {
+    let invalid_consent = || {
+        HandlerError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid consent",
+            "The consent request is unknown, has expired or belongs to another user.",
+        )
+    };
+
+    let session = Session::find(&state.redis, &jar)
+        .await?
+        .ok_or_else(invalid_consent)?;
+    let pending = PendingConsent::take(&state.redis, &req.consent_id)
+        .await?
+        .filter(|p| p.user_id == session.user_id)
+        .ok_or_else(invalid_consent)?;
+
+    match req.decision {
+        Decision::Allow => {
+            grant(
+                &state.database,
+                pending.user_id,
+                pending.client_id,
+                &pending.scope,
+            )
+            .await?;
+            Ok(Redirect::to(&pending.return_to).into_response())
+        }
+        Decision::Deny => {
+            let res = AuthorizationResponse::failed(
+                AuthorizationErrorKind::AccessDenied,
+                "The user denied the request.",
+                pending.state,
+            );
+            respond(&pending.redirect_uri, pending.response_mode, &res)
+        }
+    }
+}
+
+/// The key a pending consent is stored under in Redis.
+#[inline]
+fn redis_key(id: &str) -> String {
+    format!("oauth:consent:{id}")
+}
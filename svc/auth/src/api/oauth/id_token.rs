@@ -0,0 +1,115 @@
+//! ID tokens issued when the `openid` scope is granted.
+//!
+//! Source: https://openid.net/specs/openid-connect-core-1_0.html#IDToken
+
+use crate::config::CONFIG;
+
+use super::keys::KEYS;
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::Utc;
+use lerpz_utils::jwt::{self, Algorithm};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use uuid::Uuid;
+
+/// How long an ID token is valid for, in seconds.
+pub const ID_TOKEN_TTL: i64 = 60 * 15;
+
+/// The claims of an ID token.
+pub type Claims = jwt::Claims<IdTokenClaims>;
+
+/// Claims specific to ID tokens.
+///
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#IDToken
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IdTokenClaims {
+    /// Value used to associate a client session with the ID token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// When the user authenticated.
+    pub auth_time: i64,
+    /// Hash of the access token issued together with the ID token.
+    ///
+    /// Source: https://openid.net/specs/openid-connect-core-1_0.html#CodeIDToken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_hash: Option<String>,
+    /// Hash of the authorization code issued together with the ID token.
+    ///
+    /// Source: https://openid.net/specs/openid-connect-core-1_0.html#HybridIDToken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_hash: Option<String>,
+}
+
+/// An ID token that is about to be issued.
+#[derive(Debug, Clone)]
+pub struct NewIdToken<'a> {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    /// The `nonce` from the authorization request.
+    pub nonce: Option<&'a str>,
+    /// When the user authenticated, as a unix timestamp.
+    pub auth_time: i64,
+    /// The access token issued together with the ID token.
+    pub access_token: Option<&'a str>,
+    /// The authorization code issued together with the ID token.
+    pub code: Option<&'a str>,
+}
+
+/// Signs a new ID token.
+pub fn issue(new: NewIdToken<'_>) -> jwt::Result<String> {
+    let now = Utc::now().timestamp();
+    let alg = KEYS.current().alg();
+
+    let claims = Claims {
+        aud: new.client_id.to_string().into(),
+        iss: CONFIG.ISSUER.clone(),
+        sub: new.user_id.to_string(),
+        exp: now + ID_TOKEN_TTL,
+        nbf: now,
+        iat: now,
+        jti: None,
+        extra: IdTokenClaims {
+            nonce: new.nonce.map(Into::into),
+            auth_time: new.auth_time,
+            at_hash: new.access_token.map(|token| half_hash(alg, token)),
+            c_hash: new.code.map(|code| half_hash(alg, code)),
+        },
+    };
+
+    KEYS.encode(&claims)
+}
+
+/// Hashes a token for the `at_hash` and `c_hash` claims.
+///
+/// This is the left-most half of the hash of the token, using the hash
+/// function of the algorithm the ID token is signed with.
+///
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#CodeIDToken
+fn half_hash(alg: Algorithm, token: &str) -> String {
+    let digest = match alg {
+        Algorithm::HS384 | Algorithm::RS384 | Algorithm::PS384 | Algorithm::ES384 => {
+            Sha384::digest(token).to_vec()
+        }
+        Algorithm::HS512 | Algorithm::RS512 | Algorithm::PS512 | Algorithm::EdDSA => {
+            Sha512::digest(token).to_vec()
+        }
+        _ => Sha256::digest(token).to_vec(),
+    };
+    BASE64_URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_hash() {
+        // Source: https://openid.net/specs/openid-connect-core-1_0.html#code-id_tokenExample
+        let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+        assert_eq!(
+            half_hash(Algorithm::RS256, access_token),
+            "77QmUPtjPfzWtF2AnpK9RQ"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Keys used for signing and verifying tokens issued by this server.
+//!
+//! Keys are read from PEM files in `JWT_KEYS_DIR`, where the file name
+//! without the `.pem` extension is used as the `kid`. The key named by
+//! `JWT_KEY_ID` signs new tokens, while the others are only used for
+//! verifying tokens signed before a rotation.
+
+use std::{path::Path, sync::LazyLock};
+
+use crate::config::CONFIG;
+
+use anyhow::Context;
+use lerpz_utils::jwt::{Key, KeySet};
+
+/// The keys of this server.
+pub static KEYS: LazyLock<KeySet> = LazyLock::new(|| {
+    load(&CONFIG.JWT_KEYS_DIR, &CONFIG.JWT_KEY_ID)
+        .unwrap_or_else(|err| panic!("can't load signing keys: {err:#}"))
+});
+
+fn load(dir: &Path, current: &str) -> anyhow::Result<KeySet> {
+    let mut previous = Vec::new();
+    let mut current_key = None;
+
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    for path in paths {
+        if path.extension().is_none_or(|ext| ext != "pem") {
+            continue;
+        }
+
+        let kid = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .with_context(|| format!("invalid key name {}", path.display()))?;
+        let pem = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        let key =
+            Key::from_pem(kid, &pem).with_context(|| format!("loading {}", path.display()))?;
+
+        if kid == current {
+            current_key = Some(key);
+        } else {
+            previous.push(key);
+        }
+    }
+
+    let current_key = current_key
+        .with_context(|| format!("no key named \"{current}.pem\" in {}", dir.display()))?;
+
+    Ok(previous
+        .into_iter()
+        .fold(KeySet::new(current_key), KeySet::with_previous))
+}
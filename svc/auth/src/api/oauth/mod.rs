@@ -1,17 +1,58 @@
+mod access_token;
 mod authorize;
+mod client;
+mod code;
+mod consent;
+mod id_token;
+pub mod keys;
+pub mod password;
+mod pkce;
+mod refresh_token;
 mod revoke;
+mod scope;
 mod token;
 mod userinfo;
 
+pub use access_token::bearer_auth;
+pub use refresh_token::revoke_user;
+
 use crate::AppState;
 
+use access_token::AccessTokenClaims;
 use axum::routing::{get, post};
+use lerpz_utils::axum::middelware::bearer::RequireScope;
+use rand::{Rng, distr::Alphanumeric};
+
+/// Length of generated codes and opaque tokens.
+///
+/// 43 alphanumeric characters gives a little more than 256 bits of entropy.
+const SECRET_LEN: usize = 43;
 
 pub fn router(state: AppState) -> axum::Router<AppState> {
     axum::Router::<AppState>::new()
         .route("/authorize", get(authorize::handler))
+        .route("/consent", post(consent::handler))
         .route("/token", post(token::handler))
         .route("/revoke", post(revoke::handler))
-        .route("/userinfo", get(userinfo::handler))
+        .route(
+            "/userinfo",
+            get(userinfo::handler).post(userinfo::handler).layer(
+                RequireScope::<AccessTokenClaims>::new(state.bearer.clone(), "openid"),
+            ),
+        )
         .with_state(state)
 }
+
+/// Compares two byte slices without short-circuiting on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generates a random secret suitable for codes and opaque tokens.
+pub(super) fn generate_secret() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LEN)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Verification of user credentials.
+//!
+//! Attempts are counted per user in Redis before the password is checked, and
+//! further attempts are rejected for a while once too many have failed.
+//! Passwords hashed with an outdated scheme or parameters are hashed again on
+//! a successful login.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc6749#section-10.7
+
+use crate::{
+    config::{CONFIG, EmailVerification},
+    state::AppState,
+};
+
+use lerpz_core::db::User;
+use lerpz_utils::pwd::{self, PwdValidation, hash_pwd, validate_pwd};
+use redis::AsyncCommands;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+/// How many attempts are allowed within [`THROTTLE_WINDOW`] without one
+/// succeeding.
+const MAX_FAILED_ATTEMPTS: u64 = 5;
+
+/// How long attempts are remembered, in seconds.
+const THROTTLE_WINDOW: u64 = 60 * 15;
+
+/// A hash that unknown users are verified against.
+///
+/// This makes a login attempt for an unknown user take as long as one for a
+/// known user, so that the response time doesn't reveal which users exist.
+static DUMMY_HASH: OnceCell<(String, String)> = OnceCell::const_new();
+
+/// The outcome of verifying a user's credentials.
+#[derive(Debug)]
+pub enum Verified {
+    User(Box<User>),
+    Invalid,
+    /// Too many attempts have failed recently.
+    Throttled,
+    /// The credentials are valid, but the email has to be verified first.
+    Unverified,
+    /// Too many passwords are being hashed to verify this one right now.
+    Overloaded,
+}
+
+/// Verifies the password of the user with the given username or email.
+pub async fn verify_user(
+    state: &AppState,
+    login: &str,
+    password: &str,
+) -> anyhow::Result<Verified> {
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT
+        id,
+        primary_email AS email,
+        username,
+        password_hash,
+        password_salt,
+        avatar,
+        email_verified_at,
+        created_at AT TIME ZONE 'UTC' AS "created_at!",
+        updated_at AT TIME ZONE 'UTC' AS "updated_at!"
+        FROM users
+        WHERE username = $1 OR primary_email = $1"#,
+        login
+    )
+    .fetch_optional(&state.database)
+    .await?;
+
+    // The attempt is counted before the slow password check, so that
+    // concurrent attempts can't all get in before the first one fails.
+    let key = throttle_key(user.as_ref(), login);
+    let mut conn = state.redis.get_multiplexed_async_connection().await?;
+
+    let (attempts,): (u64,) = redis::pipe()
+        .atomic()
+        .incr(&key, 1)
+        .cmd("EXPIRE")
+        .arg(&key)
+        .arg(THROTTLE_WINDOW)
+        .arg("NX")
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    if attempts > MAX_FAILED_ATTEMPTS {
+        return Ok(Verified::Throttled);
+    }
+
+    let validation = match validate(user.as_ref(), password).await {
+        Ok(validation) => validation,
+        Err(pwd::Error::Overloaded) => {
+            // The password was never checked, so the attempt doesn't count.
+            let _: () = conn.decr(&key, 1).await?;
+            return Ok(Verified::Overloaded);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    match user {
+        Some(user) if validation.is_valid() => {
+            let _: () = conn.del(&key).await?;
+            if validation.needs_rehash()
+                && let Err(err) = rehash(state, &user, password).await
+            {
+                tracing::warn!(user_id = %user.id, error = %err, "failed rehashing password");
+            }
+            if CONFIG.EMAIL_VERIFICATION == EmailVerification::Required && !user.is_verified() {
+                return Ok(Verified::Unverified);
+            }
+            Ok(Verified::User(Box::new(user)))
+        }
+        _ => Ok(Verified::Invalid),
+    }
+}
+
+/// Validates the password of the user, or of a dummy user if there is none.
+async fn validate(user: Option<&User>, password: &str) -> pwd::Result<PwdValidation> {
+    match user {
+        Some(user) => {
+            validate_pwd(
+                &user.password_hash,
+                password,
+                Some(user.password_salt.as_str()),
+            )
+            .await
+        }
+        None => {
+            let (hash, salt) = dummy_hash().await?;
+            validate_pwd(hash, password, Some(salt.as_str())).await?;
+            Ok(PwdValidation::Invalid)
+        }
+    }
+}
+
+/// Replaces the outdated password hash of the user.
+///
+/// The hash is only replaced if it hasn't changed since it was validated.
+async fn rehash(state: &AppState, user: &User, password: &str) -> anyhow::Result<()> {
+    let password_salt = Uuid::new_v4().to_string();
+    let password_hash = hash_pwd(password, &password_salt).await?;
+
+    sqlx::query!(
+        "UPDATE users SET password_hash = $3, password_salt = $4
+        WHERE id = $1 AND password_hash = $2",
+        user.id,
+        user.password_hash,
+        password_hash,
+        password_salt
+    )
+    .execute(&state.database)
+    .await?;
+
+    Ok(())
+}
+
+async fn dummy_hash() -> pwd::Result<&'static (String, String)> {
+    DUMMY_HASH
+        .get_or_try_init(|| async {
+            let salt = Uuid::new_v4().to_string();
+            let hash = hash_pwd(Uuid::new_v4().to_string(), &salt).await?;
+            Ok((hash, salt))
+        })
+        .await
+}
+
+/// The key attempts for a user are counted under in Redis.
+///
+/// Known users are counted by id, so that their username and email share the
+/// same counter. Unknown logins are counted by their normalized form.
+fn throttle_key(user: Option<&User>, login: &str) -> String {
+    match user {
+        Some(user) => format!("oauth:login:failed:{}", user.id),
+        None => format!("oauth:login:failed:{}", login.trim().to_lowercase()),
+    }
+}
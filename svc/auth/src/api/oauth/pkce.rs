@@ -0,0 +1,110 @@
+//! Proof Key for Code Exchange (PKCE).
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc7636
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How the code verifier is transformed into the code challenge.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.2
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeChallengeMethod {
+    #[serde(rename = "plain")]
+    Plain,
+    #[serde(rename = "S256")]
+    S256,
+}
+
+/// A code challenge bound to an authorization code.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CodeChallenge {
+    pub challenge: String,
+    pub method: CodeChallengeMethod,
+}
+
+impl CodeChallenge {
+    /// Creates a code challenge from the parameters of an authorization
+    /// request.
+    ///
+    /// The method defaults to `plain` when it is missing. Returns a description
+    /// of the problem if the parameters are invalid.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.3
+    pub fn new(challenge: &str, method: Option<&str>) -> Result<Self, &'static str> {
+        let method = match method {
+            None | Some("plain") => CodeChallengeMethod::Plain,
+            Some("S256") => CodeChallengeMethod::S256,
+            Some(_) => return Err("Unsupported code challenge method."),
+        };
+
+        if !is_valid_code(challenge) {
+            return Err("The code challenge is malformed.");
+        }
+
+        Ok(Self {
+            challenge: challenge.into(),
+            method,
+        })
+    }
+
+    /// Checks a code verifier against the challenge.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.6
+    pub fn verify(&self, verifier: &str) -> bool {
+        if !is_valid_code(verifier) {
+            return false;
+        }
+
+        let expected = match self.method {
+            CodeChallengeMethod::Plain => verifier.to_string(),
+            CodeChallengeMethod::S256 => BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(verifier)),
+        };
+
+        super::constant_time_eq(expected.as_bytes(), self.challenge.as_bytes())
+    }
+}
+
+/// Checks that a verifier or challenge only uses unreserved characters and has
+/// a length between 43 and 128 characters.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.1
+fn is_valid_code(code: &str) -> bool {
+    (43..=128).contains(&code.len())
+        && code
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7636#appendix-B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn test_s256_challenge() {
+        let challenge = CodeChallenge::new(CHALLENGE, Some("S256")).unwrap();
+
+        assert!(challenge.verify(VERIFIER));
+        assert!(!challenge.verify(CHALLENGE));
+    }
+
+    #[test]
+    fn test_plain_challenge() {
+        let challenge = CodeChallenge::new(VERIFIER, None).unwrap();
+
+        assert_eq!(challenge.method, CodeChallengeMethod::Plain);
+        assert!(challenge.verify(VERIFIER));
+        assert!(!challenge.verify(CHALLENGE));
+    }
+
+    #[test]
+    fn test_invalid_challenge() {
+        assert!(CodeChallenge::new("too-short", Some("S256")).is_err());
+        assert!(CodeChallenge::new(CHALLENGE, Some("S512")).is_err());
+    }
+}
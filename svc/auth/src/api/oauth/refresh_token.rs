@@ -0,0 +1,187 @@
+//! Opaque refresh tokens issued by the token endpoint.
+//!
+//! Only a hash of each token is stored. Tokens are rotated every time they are
+//! used, and presenting a token that has already been rotated revokes the
+//! whole family of tokens descending from the same grant.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc9700#section-4.14.2
+
+use super::{access_token, generate_secret};
+
+use chrono::{DateTime, Duration, Utc};
+use lerpz_core::db::RefreshToken;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+/// How long a refresh token is valid for, in seconds.
+pub const REFRESH_TOKEN_TTL: i64 = 60 * 60 * 24 * 30;
+
+/// A refresh token that is about to be issued.
+#[derive(Debug, Clone)]
+pub struct NewRefreshToken<'a> {
+    pub family_id: Uuid,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub scope: Option<&'a str>,
+    /// The ID of the access token issued together with the refresh token.
+    pub access_token_jti: &'a str,
+    /// When the access token issued together with the refresh token expires.
+    pub access_token_exp: i64,
+}
+
+/// Stores a new refresh token and returns the token given to the client.
+pub async fn issue(db: impl PgExecutor<'_>, new: NewRefreshToken<'_>) -> anyhow::Result<String> {
+    let token = generate_secret();
+    let access_token_expires_at = DateTime::from_timestamp(new.access_token_exp, 0)
+        .ok_or_else(|| anyhow::anyhow!("access token expiry is out of range"))?;
+    let expires_at = Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL);
+
+    sqlx::query!(
+        "INSERT INTO refresh_tokens (
+        token_hash,
+        family_id,
+        client_id,
+        user_id,
+        scope,
+        access_token_jti,
+        access_token_expires_at,
+        expires_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        hash(&token),
+        new.family_id,
+        new.client_id,
+        new.user_id,
+        new.scope,
+        new.access_token_jti,
+        access_token_expires_at,
+        expires_at
+    )
+    .execute(db)
+    .await?;
+
+    Ok(token)
+}
+
+/// Finds a refresh token.
+pub async fn find(db: impl PgExecutor<'_>, token: &str) -> sqlx::Result<Option<RefreshToken>> {
+    sqlx::query_as!(
+        RefreshToken,
+        "SELECT * FROM refresh_tokens WHERE token_hash = $1",
+        hash(token)
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Finds a refresh token and locks it until the transaction ends.
+pub async fn find_for_update(
+    db: impl PgExecutor<'_>,
+    token: &str,
+) -> sqlx::Result<Option<RefreshToken>> {
+    sqlx::query_as!(
+        RefreshToken,
+        "SELECT * FROM refresh_tokens WHERE token_hash = $1 FOR UPDATE",
+        hash(token)
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Marks a refresh token as exchanged for a new one.
+pub async fn mark_rotated(db: impl PgExecutor<'_>, id: Uuid) -> sqlx::Result<()> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET rotated_at = CURRENT_TIMESTAMP WHERE id = $1",
+        id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Revokes every refresh token in a family.
+///
+/// The access tokens issued together with the refresh tokens are returned
+/// like in [`revoke_user`].
+pub async fn revoke_family(
+    db: &mut PgConnection,
+    family_id: Uuid,
+) -> anyhow::Result<RevokedAccessTokens> {
+    let issued = sqlx::query!(
+        "UPDATE refresh_tokens
+        SET revoked_at = COALESCE(revoked_at, CURRENT_TIMESTAMP)
+        WHERE family_id = $1
+        RETURNING access_token_jti, access_token_expires_at",
+        family_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(RevokedAccessTokens(
+        issued
+            .into_iter()
+            .map(|row| {
+                (
+                    row.access_token_jti,
+                    row.access_token_expires_at.timestamp(),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Revokes every refresh token issued to the user.
+///
+/// The access tokens issued together with the refresh tokens are returned
+/// instead of being revoked right away, so that they can be added to the
+/// denylist once the transaction revoking the refresh tokens is committed.
+pub async fn revoke_user(
+    db: &mut PgConnection,
+    user_id: Uuid,
+) -> anyhow::Result<RevokedAccessTokens> {
+    let issued = sqlx::query!(
+        "UPDATE refresh_tokens
+        SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND revoked_at IS NULL
+        RETURNING access_token_jti, access_token_expires_at",
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(RevokedAccessTokens(
+        issued
+            .into_iter()
+            .map(|row| {
+                (
+                    row.access_token_jti,
+                    row.access_token_expires_at.timestamp(),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// The IDs and expiry of access tokens that still have to be revoked.
+#[must_use]
+pub struct RevokedAccessTokens(Vec<(String, i64)>);
+
+impl RevokedAccessTokens {
+    /// Adds the access tokens to the denylist.
+    pub async fn deny(self, redis: &redis::Client) -> anyhow::Result<()> {
+        for (jti, exp) in self.0 {
+            access_token::revoke(redis, &jti, exp).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes a refresh token for storage.
+///
+/// Refresh tokens have enough entropy that a fast hash is sufficient.
+#[inline]
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token))
+}
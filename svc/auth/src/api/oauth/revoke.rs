@@ -1,6 +1,113 @@
-use lerpz_utils::axum::error::HandlerResult;
+use crate::state::AppState;
 
+use super::{
+    access_token,
+    client::authenticate_client,
+    refresh_token,
+    token::error::{TokenError, TokenResult},
+};
+
+use lerpz_core::db::OAuthClient;
+
+use axum::{
+    Form,
+    extract::{State, rejection::FormRejection},
+    http::HeaderMap,
+};
+use serde::Deserialize;
+
+/// A request to revoke an access or refresh token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7009#section-2.1
+#[derive(Deserialize, Debug)]
+pub struct RevocationRequest {
+    token: String,
+    /// Either `access_token` or `refresh_token`.
+    ///
+    /// This is only a hint for where to look first. Unknown hints are ignored.
+    token_type_hint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// Revokes a token issued to the authenticated client.
+///
+/// Responds with `200 OK` even if the token is unknown, invalid or issued to
+/// another client, so that the endpoint can't be used for probing tokens.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7009#section-2.2
 #[axum::debug_handler]
-pub async fn handler() -> HandlerResult<()> {
-    todo!()
+pub async fn handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    form: Result<Form<RevocationRequest>, FormRejection>,
+) -> TokenResult<()> {
+    let Form(req) = form.map_err(|err| TokenError::invalid_request(err.body_text()))?;
+
+    let client = authenticate_client(
+        &state,
+        &headers,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+
+    if req.token_type_hint.as_deref() == Some("access_token") {
+        if !revoke_access_token(&state, &client, &req.token).await? {
+            revoke_refresh_token(&state, &client, &req.token).await?;
+        }
+    } else if !revoke_refresh_token(&state, &client, &req.token).await? {
+        revoke_access_token(&state, &client, &req.token).await?;
+    }
+
+    Ok(())
+}
+
+/// Revokes an access token if it is valid and was issued to the client.
+///
+/// Returns whether the token was a valid access token.
+async fn revoke_access_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> anyhow::Result<bool> {
+    let Ok(claims) = access_token::decode(token) else {
+        return Ok(false);
+    };
+
+    let client_id = client.id.to_string();
+    if let Some(jti) = claims.jti.as_deref()
+        && claims.extra.client_id == client_id
+    {
+        access_token::revoke(&state.redis, jti, claims.exp).await?;
+    }
+
+    Ok(true)
+}
+
+/// Revokes a refresh token if it was issued to the client.
+///
+/// Every token in the family, and the access tokens issued together with them,
+/// are revoked as well. Returns whether the token was a known refresh token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7009#section-2.1
+async fn revoke_refresh_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> anyhow::Result<bool> {
+    let mut db = state.database.acquire().await?;
+
+    let Some(stored) = refresh_token::find(&mut *db, token).await? else {
+        return Ok(false);
+    };
+
+    if stored.client_id == client.id {
+        refresh_token::revoke_family(&mut db, stored.family_id)
+            .await?
+            .deny(&state.redis)
+            .await?;
+    }
+
+    Ok(true)
 }
@@ -0,0 +1,26 @@
+//! Helpers for working with space-delimited scopes.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc6749#section-3.3
+
+/// Returns an iterator over the individual scopes in a scope string.
+pub fn split(scope: &str) -> impl Iterator<Item = &str> {
+    scope.split_ascii_whitespace()
+}
+
+/// Whether every scope in `requested` is also part of `granted`.
+pub fn is_subset(requested: &str, granted: &str) -> bool {
+    split(requested).all(|s| split(granted).any(|g| g == s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_subset() {
+        assert!(is_subset("openid", "openid profile"));
+        assert!(is_subset("", "openid"));
+        assert!(is_subset("profile  openid", "openid profile"));
+        assert!(!is_subset("openid email", "openid profile"));
+    }
+}
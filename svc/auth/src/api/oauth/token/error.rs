@@ -0,0 +1,122 @@
+//! Errors returned by the token endpoint.
+//!
+//! Source: https://datatracker.ietf.org/doc/html/rfc6749#section-5.2
+
+use std::borrow::Cow;
+
+use lerpz_utils::axum::error::HandlerError;
+
+use axum::{
+    Json,
+    http::{HeaderName, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+/// A type alias for [`Result<T, TokenError>`].
+pub type TokenResult<T> = std::result::Result<T, TokenError>;
+
+/// Headers that keep any response of the token endpoint out of caches.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-5.1
+pub const NO_STORE: [(HeaderName, &str); 2] = [
+    (header::CACHE_CONTROL, "no-store"),
+    (header::PRAGMA, "no-cache"),
+];
+
+/// Possible errors that can be returned by the token endpoint.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-5.2
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenErrorKind {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    InvalidScope,
+}
+
+/// The body of an error response from the token endpoint.
+#[derive(Serialize, Debug)]
+pub struct TokenErrorResponse {
+    error: TokenErrorKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_description: Option<Cow<'static, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_uri: Option<Cow<'static, str>>,
+}
+
+/// An error returned by the token endpoint.
+#[derive(Debug)]
+pub enum TokenError {
+    /// The request was rejected with one of the errors defined by RFC 6749.
+    Rejected(TokenErrorResponse),
+    /// Something unexpected went wrong while handling the request.
+    Internal(HandlerError),
+}
+
+impl TokenError {
+    /// Create a new [`TokenError`] with an error kind and a description.
+    pub fn new(error: TokenErrorKind, description: impl Into<Cow<'static, str>>) -> Self {
+        Self::Rejected(TokenErrorResponse {
+            error,
+            error_description: Some(description.into()),
+            error_uri: None,
+        })
+    }
+
+    /// The request is missing a parameter or is otherwise malformed.
+    pub fn invalid_request(description: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(TokenErrorKind::InvalidRequest, description)
+    }
+
+    /// Client authentication failed.
+    pub fn invalid_client() -> Self {
+        Self::new(
+            TokenErrorKind::InvalidClient,
+            "Client authentication failed.",
+        )
+    }
+
+    /// The grant is invalid, expired, revoked or issued to another client.
+    pub fn invalid_grant(description: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(TokenErrorKind::InvalidGrant, description)
+    }
+}
+
+impl IntoResponse for TokenError {
+    /// Converts a [`TokenError`] into a [`Response`].
+    ///
+    /// Failed client authentication results in `401 Unauthorized` together
+    /// with a `WWW-Authenticate` header, all other errors in `400 Bad Request`.
+    fn into_response(self) -> Response {
+        let res = match self {
+            Self::Rejected(res) => res,
+            Self::Internal(err) => return err.into_response(),
+        };
+
+        if res.error == TokenErrorKind::InvalidClient {
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, r#"Basic realm="token""#)],
+                NO_STORE,
+                Json(res),
+            )
+                .into_response()
+        } else {
+            (StatusCode::BAD_REQUEST, NO_STORE, Json(res)).into_response()
+        }
+    }
+}
+
+impl<E> From<E> for TokenError
+where
+    E: Into<anyhow::Error>,
+{
+    /// Turns any error into an internal [`TokenError`].
+    fn from(value: E) -> Self {
+        Self::Internal(HandlerError::from(value))
+    }
+}
@@ -0,0 +1,576 @@
+/// Errors returned by the token endpoint.
+pub mod error;
+
+use crate::{api::overloaded, state::AppState};
+
+use super::{
+    access_token::{self, ACCESS_TOKEN_TTL},
+    client::authenticate_client,
+    code::{self, Consumed, UsedAuthorizationCode},
+    id_token::{self, NewIdToken},
+    password::{self, Verified},
+    refresh_token::{self, NewRefreshToken},
+    scope,
+};
+
+use error::{NO_STORE, TokenError, TokenErrorKind, TokenResult};
+
+use axum::{
+    Form, Json,
+    extract::{FromRequest, Request, State},
+    http::{HeaderMap, HeaderName},
+};
+use chrono::Utc;
+use lerpz_core::db::OAuthClient;
+use serde::{
+    Deserialize, Serialize,
+    de::value::{Error as DeError, MapDeserializer},
+};
+use uuid::Uuid;
+
+/// The grant types supported by the token endpoint.
+const GRANT_TYPES: &[&str] = &[
+    "authorization_code",
+    "password",
+    "client_credentials",
+    "refresh_token",
+];
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "grant_type")]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum GrantRequest {
+    AuthorizationCode(AuthorizationCodeRequest),
+    #[serde(rename = "password")]
+    PasswordCredentials(PasswordCredentialsRequest),
+    ClientCredentials(ClientCredentialsRequest),
+    RefreshToken(RefreshTokenRequest),
+}
+
+/// A request to exchange an authorization code for an access token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.3
+#[derive(Deserialize, Debug)]
+pub struct AuthorizationCodeRequest {
+    code: String,
+    redirect_uri: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    /// The PKCE code verifier.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc7636#section-4.5
+    code_verifier: Option<String>,
+}
+
+/// A request to exchange username and password for an access token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.3.2
+#[derive(Deserialize, Debug)]
+pub struct PasswordCredentialsRequest {
+    password: String,
+    username: String,
+    scope: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// A request to exchange client credentials for an access token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.4.2
+#[derive(Deserialize, Debug)]
+pub struct ClientCredentialsRequest {
+    scope: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// A request to exchange a refresh token for a new access token.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-6
+#[derive(Deserialize, Debug)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+    /// Can only narrow the scope of the original grant.
+    scope: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+}
+
+/// A response containing an access token, refresh token, and other metadata.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-5.1
+#[derive(Serialize, Debug)]
+pub struct AccessTokenResponse {
+    /// The access token issued by the authorization server.
+    access_token: String,
+    /// The type of the token issued, typically "Bearer".
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-7.1
+    token_type: String,
+    /// The lifetime in seconds of the access token.
+    ///
+    /// This might not be present if the lifetime is defined within the token
+    /// itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_in: Option<u64>,
+    /// A token that can be used to obtain a new access token without re-authenticating.
+    ///
+    /// This is not always present, depending on the grant type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    /// Might not be present if the scope is the same as the one requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    /// Only present when the `openid` scope was granted.
+    ///
+    /// Source: https://openid.net/specs/openid-connect-core-1_0.html#TokenResponse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token: Option<String>,
+}
+
+impl<S> FromRequest<S> for GrantRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = TokenError;
+
+    /// Parses the form body of a token request.
+    ///
+    /// Unlike [`Form`] this reports problems with the request using the errors
+    /// defined by RFC 6749, so that an unknown `grant_type` results in
+    /// `unsupported_grant_type` instead of a generic rejection.
+    ///
+    /// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-3.2
+    async fn from_request(r: Request, s: &S) -> Result<Self, Self::Rejection> {
+        let Form(params) = Form::<Vec<(String, String)>>::from_request(r, s)
+            .await
+            .map_err(|_| TokenError::invalid_request("The request body is not a valid form."))?;
+
+        for (i, (key, _)) in params.iter().enumerate() {
+            if params[..i].iter().any(|(other, _)| other == key) {
+                return Err(TokenError::invalid_request(format!(
+                    "The \"{key}\" parameter is included more than once."
+                )));
+            }
+        }
+
+        match params.iter().find(|(key, _)| key == "grant_type") {
+            None => {
+                return Err(TokenError::invalid_request(
+                    "The \"grant_type\" parameter is missing.",
+                ));
+            }
+            Some((_, grant_type)) if !GRANT_TYPES.contains(&grant_type.as_str()) => {
+                return Err(TokenError::new(
+                    TokenErrorKind::UnsupportedGrantType,
+                    format!("The grant type \"{grant_type}\" is not supported."),
+                ));
+            }
+            Some(_) => {}
+        }
+
+        GrantRequest::deserialize(MapDeserializer::<_, DeError>::new(params.into_iter()))
+            .map_err(|err| TokenError::invalid_request(err.to_string()))
+    }
+}
+
+#[axum::debug_handler]
+pub async fn handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: GrantRequest,
+) -> TokenResult<([(HeaderName, &'static str); 2], Json<AccessTokenResponse>)> {
+    let access_token = match body {
+        GrantRequest::AuthorizationCode(req) => authorization_code(&state, &headers, req).await,
+        GrantRequest::PasswordCredentials(req) => password_credentials(&state, &headers, req).await,
+        GrantRequest::ClientCredentials(req) => client_credentials(&state, &headers, req).await,
+        GrantRequest::RefreshToken(req) => refresh_token(&state, &headers, req).await,
+    }?;
+
+    Ok((NO_STORE, Json(access_token)))
+}
+
+async fn authorization_code(
+    state: &AppState,
+    headers: &HeaderMap,
+    req: AuthorizationCodeRequest,
+) -> TokenResult<AccessTokenResponse> {
+    let client = authenticate_client(
+        state,
+        headers,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+    ensure_grant(&client, "authorization_code")?;
+
+    let used = UsedAuthorizationCode {
+        jti: Uuid::new_v4().to_string(),
+        exp: Utc::now().timestamp() + ACCESS_TOKEN_TTL,
+        family_id: Uuid::new_v4(),
+    };
+
+    let code = match code::consume(&state.redis, &req.code, &used).await? {
+        Consumed::Fresh(code) => code,
+        Consumed::Replayed(used) => {
+            // A code that is used twice might have been stolen, so everything
+            // issued from it gets revoked.
+            //
+            // Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2
+            tracing::warn!(client_id = %client.id, "authorization code was replayed");
+            let mut db = state.database.acquire().await?;
+            access_token::revoke(&state.redis, &used.jti, used.exp).await?;
+            refresh_token::revoke_family(&mut db, used.family_id)
+                .await?
+                .deny(&state.redis)
+                .await?;
+            return Err(TokenError::invalid_grant(
+                "The authorization code has already been used.",
+            ));
+        }
+        Consumed::Unknown => {
+            return Err(TokenError::invalid_grant(
+                "The authorization code is invalid or expired.",
+            ));
+        }
+    };
+
+    if code.client_id != client.id || code.redirect_uri != req.redirect_uri {
+        return Err(TokenError::invalid_grant(
+            "The authorization code was not issued to this client or redirect URI.",
+        ));
+    }
+
+    match (&code.code_challenge, req.code_verifier.as_deref()) {
+        (Some(challenge), Some(verifier)) if challenge.verify(verifier) => {}
+        (None, None) if !client.requires_pkce() => {}
+        _ => {
+            return Err(TokenError::invalid_grant(
+                "The code verifier is missing or invalid.",
+            ));
+        }
+    }
+
+    let user_id = code.user_id;
+
+    let access_token = access_token::issue(
+        &used.jti,
+        user_id.to_string(),
+        client.id,
+        code.scope.as_deref(),
+    )?;
+
+    let refresh_token = refresh_token::issue(
+        &state.database,
+        NewRefreshToken {
+            family_id: used.family_id,
+            client_id: client.id,
+            user_id,
+            scope: code.scope.as_deref(),
+            access_token_jti: &used.jti,
+            access_token_exp: used.exp,
+        },
+    )
+    .await?;
+
+    let id_token = scope::split(code.scope.as_deref().unwrap_or_default())
+        .any(|s| s == "openid")
+        .then(|| {
+            id_token::issue(NewIdToken {
+                user_id,
+                client_id: client.id,
+                nonce: code.nonce.as_deref(),
+                auth_time: code.auth_time,
+                access_token: Some(&access_token),
+                code: None,
+            })
+        })
+        .transpose()?;
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_in: Some(ACCESS_TOKEN_TTL as u64),
+        refresh_token: Some(refresh_token),
+        scope: None,
+        id_token,
+    })
+}
+
+/// Issues tokens for a user that gave their username and password to the
+/// client.
+///
+/// Only clients that are explicitly allowed to can use this grant.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.3
+async fn password_credentials(
+    state: &AppState,
+    headers: &HeaderMap,
+    req: PasswordCredentialsRequest,
+) -> TokenResult<AccessTokenResponse> {
+    let client = authenticate_client(
+        state,
+        headers,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+    ensure_grant(&client, "password")?;
+
+    let scope = granted_scope(&client, req.scope.as_deref())?;
+
+    let user = match password::verify_user(state, &req.username, &req.password).await? {
+        Verified::User(user) => user,
+        Verified::Invalid => {
+            return Err(TokenError::invalid_grant(
+                "The username or password is incorrect.",
+            ));
+        }
+        Verified::Throttled => {
+            return Err(TokenError::invalid_grant(
+                "Too many failed attempts, try again later.",
+            ));
+        }
+        Verified::Unverified => {
+            return Err(TokenError::invalid_grant(
+                "The email of the user hasn't been verified.",
+            ));
+        }
+        Verified::Overloaded => return Err(TokenError::Internal(overloaded())),
+    };
+
+    let jti = Uuid::new_v4().to_string();
+    let exp = Utc::now().timestamp() + ACCESS_TOKEN_TTL;
+    let scope = Some(scope.as_str()).filter(|s| !s.is_empty());
+    let access_token = access_token::issue(&jti, user.id.to_string(), client.id, scope)?;
+
+    let refresh_token = refresh_token::issue(
+        &state.database,
+        NewRefreshToken {
+            family_id: Uuid::new_v4(),
+            client_id: client.id,
+            user_id: user.id,
+            scope,
+            access_token_jti: &jti,
+            access_token_exp: exp,
+        },
+    )
+    .await?;
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_in: Some(ACCESS_TOKEN_TTL as u64),
+        refresh_token: Some(refresh_token),
+        scope: req
+            .scope
+            .is_none()
+            .then(|| scope.unwrap_or_default().into()),
+        id_token: None,
+    })
+}
+
+/// Issues an access token on behalf of the client itself.
+///
+/// Only confidential clients can use this grant, and the token is limited to
+/// the scopes registered for the client. No refresh token is issued.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6749#section-4.4
+async fn client_credentials(
+    state: &AppState,
+    headers: &HeaderMap,
+    req: ClientCredentialsRequest,
+) -> TokenResult<AccessTokenResponse> {
+    let client = authenticate_client(
+        state,
+        headers,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+
+    if client.is_public() {
+        return Err(TokenError::new(
+            TokenErrorKind::UnauthorizedClient,
+            "Public clients can't use the client credentials grant.",
+        ));
+    }
+    ensure_grant(&client, "client_credentials")?;
+
+    let scope = granted_scope(&client, req.scope.as_deref())?;
+
+    let jti = Uuid::new_v4().to_string();
+    let access_token = access_token::issue(
+        &jti,
+        client.id.to_string(),
+        client.id,
+        Some(scope.as_str()).filter(|s| !s.is_empty()),
+    )?;
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_in: Some(ACCESS_TOKEN_TTL as u64),
+        refresh_token: None,
+        scope: req.scope.is_none().then_some(scope),
+        id_token: None,
+    })
+}
+
+async fn refresh_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    req: RefreshTokenRequest,
+) -> TokenResult<AccessTokenResponse> {
+    let client = authenticate_client(
+        state,
+        headers,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+    ensure_grant(&client, "refresh_token")?;
+
+    let mut tx = state.database.begin().await?;
+
+    let stored = refresh_token::find_for_update(&mut *tx, &req.refresh_token)
+        .await?
+        .filter(|token| token.client_id == client.id)
+        .ok_or_else(|| TokenError::invalid_grant("The refresh token is invalid."))?;
+
+    if stored.revoked_at.is_some() || stored.expires_at <= Utc::now() {
+        return Err(TokenError::invalid_grant(
+            "The refresh token has expired or been revoked.",
+        ));
+    }
+
+    if stored.rotated_at.is_some() {
+        // Either the client or an attacker is using a stolen token, and there
+        // is no way of knowing which. Revoking the family logs both out.
+        tracing::warn!(family_id = %stored.family_id, "refresh token was reused");
+        let revoked = refresh_token::revoke_family(&mut tx, stored.family_id).await?;
+        tx.commit().await?;
+        revoked.deny(&state.redis).await?;
+        return Err(TokenError::invalid_grant(
+            "The refresh token has already been used.",
+        ));
+    }
+
+    let granted = stored.scope.as_deref().unwrap_or_default();
+    let scope = match req.scope.as_deref() {
+        Some(requested) if !scope::is_subset(requested, granted) => {
+            return Err(TokenError::new(
+                TokenErrorKind::InvalidScope,
+                "The requested scope exceeds the scope of the original grant.",
+            ));
+        }
+        Some(requested) => Some(requested),
+        None => stored.scope.as_deref(),
+    };
+
+    let jti = Uuid::new_v4().to_string();
+    let exp = Utc::now().timestamp() + ACCESS_TOKEN_TTL;
+    let access_token = access_token::issue(&jti, stored.user_id.to_string(), client.id, scope)?;
+
+    refresh_token::mark_rotated(&mut *tx, stored.id).await?;
+    let refresh_token = refresh_token::issue(
+        &mut *tx,
+        NewRefreshToken {
+            family_id: stored.family_id,
+            client_id: client.id,
+            user_id: stored.user_id,
+            scope: stored.scope.as_deref(),
+            access_token_jti: &jti,
+            access_token_exp: exp,
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_in: Some(ACCESS_TOKEN_TTL as u64),
+        refresh_token: Some(refresh_token),
+        scope: req
+            .scope
+            .is_some()
+            .then(|| scope.unwrap_or_default().to_string()),
+        id_token: None,
+    })
+}
+
+/// Rejects clients that are not allowed to use `grant_type`.
+fn ensure_grant(client: &OAuthClient, grant_type: &str) -> TokenResult<()> {
+    if !client.allows_grant(grant_type) {
+        return Err(TokenError::new(
+            TokenErrorKind::UnauthorizedClient,
+            format!("The client is not allowed to use the \"{grant_type}\" grant type."),
+        ));
+    }
+    Ok(())
+}
+
+/// The scope granted for a request on behalf of the client.
+///
+/// Defaults to every scope registered for the client if none was requested.
+fn granted_scope(client: &OAuthClient, requested: Option<&str>) -> TokenResult<String> {
+    let registered = client.scopes.join(" ");
+    match requested {
+        Some(requested) if !scope::is_subset(requested, &registered) => Err(TokenError::new(
+            TokenErrorKind::InvalidScope,
+            "The requested scope exceeds the scope registered for the client.",
+        )),
+        Some(requested) => Ok(requested.to_string()),
+        None => Ok(registered),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{
+        body::{Body, to_bytes},
+        http::{StatusCode, header},
+        response::IntoResponse,
+    };
+
+    async fn reject(body: &'static str) -> (StatusCode, serde_json::Value) {
+        let req = Request::post("/oauth/token")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+
+        let res = GrantRequest::from_request(req, &())
+            .await
+            .unwrap_err()
+            .into_response();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_grant_type() {
+        let (status, body) = reject("grant_type=device_code&client_id=abc").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "unsupported_grant_type");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request() {
+        let (_, body) = reject("client_id=abc").await;
+        assert_eq!(body["error"], "invalid_request");
+
+        let (_, body) = reject("grant_type=authorization_code&client_id=abc").await;
+        assert_eq!(body["error"], "invalid_request");
+
+        let (_, body) = reject("grant_type=password&grant_type=password").await;
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
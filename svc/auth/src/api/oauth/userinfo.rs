@@ -1,6 +1,98 @@
-use lerpz_utils::axum::error::HandlerResult;
+use crate::state::AppState;
 
+use super::access_token::AccessTokenClaims;
+
+use lerpz_core::db::User;
+use lerpz_utils::axum::{
+    error::{HandlerError, HandlerResult},
+    middelware::bearer::{Authenticated, Scoped},
+};
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderValue, header},
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Standard claims about the authenticated user.
+///
+/// Which claims are included depends on the scopes granted to the token.
+///
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#UserInfoResponse
+#[derive(Serialize, Debug)]
+pub struct UserInfoResponse {
+    sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email_verified: Option<bool>,
+}
+
+/// Returns claims about the user the access token was issued for.
+///
+/// Accepts both `GET` and `POST` with the token in the `Authorization` header.
+/// The token has to be granted the `openid` scope.
+///
+/// Source: https://openid.net/specs/openid-connect-core-1_0.html#UserInfo
 #[axum::debug_handler]
-pub async fn handler() -> HandlerResult<()> {
-    todo!()
+pub async fn handler(
+    State(state): State<AppState>,
+    Authenticated(claims): Authenticated<AccessTokenClaims>,
+) -> HandlerResult<Json<UserInfoResponse>> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid_token())?;
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT
+        id,
+        primary_email AS email,
+        username,
+        password_hash,
+        password_salt,
+        avatar,
+        email_verified_at,
+        created_at AT TIME ZONE 'UTC' AS "created_at!",
+        updated_at AT TIME ZONE 'UTC' AS "updated_at!"
+        FROM users
+        WHERE id = $1"#,
+        user_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or_else(invalid_token)?;
+
+    let mut res = UserInfoResponse {
+        sub: user.id.to_string(),
+        preferred_username: None,
+        picture: None,
+        email: None,
+        email_verified: None,
+    };
+
+    if claims.extra.has_scope("profile") {
+        res.preferred_username = Some(user.username);
+        res.picture = user.avatar;
+    }
+
+    if claims.extra.has_scope("email") {
+        res.email_verified = Some(user.email_verified_at.is_some());
+        res.email = Some(user.email);
+    }
+
+    Ok(Json(res))
+}
+
+/// The access token is expired, revoked, malformed or otherwise invalid.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc6750#section-3.1
+fn invalid_token() -> HandlerError {
+    HandlerError::unauthorized().with_header(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static(r#"Bearer error="invalid_token""#),
+    )
 }
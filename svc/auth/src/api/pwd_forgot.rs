@@ -1,6 +1,127 @@
-use lerpz_utils::axum::error::HandlerResult;
+//! Requesting a link to reset a forgotten password.
+//!
+//! Reset tokens are stored in Redis under a hash of the token, and expire
+//! after a short while. They can only be used once. The tokens of each user
+//! are kept in a set as well, so that all of them can be discarded once the
+//! password has been reset.
 
+use crate::{
+    mail::{TEMPLATES, preferred_locales},
+    state::AppState,
+};
+
+use super::{frontend_url, oauth::generate_secret};
+
+use lerpz_utils::axum::{error::HandlerResult, middelware::validate::Validated};
+
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use redis::AsyncCommands;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use validator::Validate;
+
+/// How long a reset link is valid for, in seconds.
+const RESET_TOKEN_TTL: u64 = 60 * 30;
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email format"))]
+    email: String,
+}
+
+/// Mails a link to reset the password to the user with the given email.
+///
+/// The response is always the same, and the email is sent in the background,
+/// so that this can't be used to find out which emails are registered.
 #[axum::debug_handler]
-pub async fn handler() -> HandlerResult<()> {
-    todo!()
+pub async fn handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Validated(Json(body)): Validated<Json<ForgotPasswordRequest>>,
+) -> HandlerResult<StatusCode> {
+    let locales = preferred_locales(&headers);
+    tokio::spawn(async move {
+        if let Err(err) = send_reset(&state, &body.email, &locales).await {
+            tracing::error!(error = %err, "failed sending password reset email");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn send_reset(state: &AppState, email: &str, locales: &[String]) -> anyhow::Result<()> {
+    let user = sqlx::query!("SELECT id FROM users WHERE primary_email = $1", email)
+        .fetch_optional(&state.database)
+        .await?;
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    let token = generate_secret();
+    let mut conn = state.redis.get_multiplexed_async_connection().await?;
+    let _: () = redis::pipe()
+        .atomic()
+        .set_ex(reset_key(&token), user.id.to_string(), RESET_TOKEN_TTL)
+        .sadd(user_resets_key(user.id), reset_key(&token))
+        .expire(user_resets_key(user.id), RESET_TOKEN_TTL as i64)
+        .query_async(&mut conn)
+        .await?;
+
+    let link = frontend_url("reset-password", &[("token", &token)]);
+    let mail = TEMPLATES.render("reset_password", locales, email, &[("link", link.as_str())])?;
+    state.mailer.send(&mail).await?;
+
+    Ok(())
+}
+
+/// Returns the user a reset token was issued to, without using it up.
+pub async fn find_reset_token(redis: &redis::Client, token: &str) -> anyhow::Result<Option<Uuid>> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let user_id: Option<String> = conn.get(reset_key(token)).await?;
+
+    Ok(user_id.map(|id| Uuid::parse_str(&id)).transpose()?)
+}
+
+/// Removes a reset token and returns the user it was issued to.
+pub async fn take_reset_token(redis: &redis::Client, token: &str) -> anyhow::Result<Option<Uuid>> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let user_id: Option<String> = conn.get_del(reset_key(token)).await?;
+
+    Ok(user_id.map(|id| Uuid::parse_str(&id)).transpose()?)
+}
+
+/// Discards every reset token issued to the user.
+pub async fn discard_reset_tokens(redis: &redis::Client, user_id: Uuid) -> anyhow::Result<()> {
+    let mut conn = redis.get_multiplexed_async_connection().await?;
+    let keys: Vec<String> = conn.smembers(user_resets_key(user_id)).await?;
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for key in &keys {
+        pipe.del(key).ignore();
+    }
+    pipe.del(user_resets_key(user_id)).ignore();
+    let _: () = pipe.query_async(&mut conn).await?;
+
+    Ok(())
+}
+
+/// The key a reset token is stored under in Redis.
+///
+/// Only a hash of the token is used, so that the tokens can't be read back
+/// from Redis.
+#[inline]
+fn reset_key(token: &str) -> String {
+    format!("pwd-reset:{:x}", Sha256::digest(token))
+}
+
+/// The key of the set of reset tokens issued to a user.
+#[inline]
+fn user_resets_key(user_id: Uuid) -> String {
+    format!("pwd-reset:user:{user_id}")
 }
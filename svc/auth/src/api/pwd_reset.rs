@@ -1,6 +1,111 @@
-use lerpz_utils::axum::error::HandlerResult;
+//! Resetting a forgotten password with a token from a reset link.
 
+use crate::state::AppState;
+
+use super::{
+    oauth::revoke_user,
+    pwd_error,
+    pwd_forgot::{discard_reset_tokens, find_reset_token, take_reset_token},
+    session::Session,
+};
+
+use lerpz_utils::{
+    axum::{
+        error::{HandlerError, HandlerResult},
+        middelware::validate::Validated,
+    },
+    pwd::{hash_pwd, policy::policy},
+};
+
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct ResetPasswordRequest {
+    token: String,
+    #[validate(length(
+        min = 8,
+        max = 128,
+        message = "Password must be between 8 and 128 characters"
+    ))]
+    password: String,
+}
+
+/// Sets a new password for the user the reset token was issued to.
+///
+/// The password is checked against the password policy once the user is
+/// known, so that it can't contain their username or email.
+///
+/// Every refresh token and session of the user is revoked, so that whoever
+/// knew the old password loses access, and any other reset links sent to the
+/// user stop working.
 #[axum::debug_handler]
-pub async fn handler() -> HandlerResult<()> {
-    todo!()
+pub async fn handler(
+    State(state): State<AppState>,
+    Validated(Json(body)): Validated<Json<ResetPasswordRequest>>,
+) -> HandlerResult<()> {
+    let invalid_token = || {
+        HandlerError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid token",
+            "The reset token is unknown, has expired or has already been used.",
+        )
+    };
+
+    let user_id = find_reset_token(&state.redis, &body.token)
+        .await?
+        .ok_or_else(invalid_token)?;
+    let user = sqlx::query!(
+        "SELECT username, primary_email FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_optional(&state.database)
+    .await?
+    .ok_or_else(invalid_token)?;
+
+    policy()
+        .check(&body.password, &[&user.username, &user.primary_email])
+        .map_err(|err| {
+            HandlerError::new(
+                StatusCode::BAD_REQUEST,
+                "Validation failed",
+                err.message
+                    .unwrap_or_else(|| "Password isn't allowed".into()),
+            )
+        })?;
+
+    let password_salt = Uuid::new_v4().to_string();
+    let password_hash = hash_pwd(body.password, &password_salt)
+        .await
+        .map_err(pwd_error)?;
+
+    // The token is only used up once the password is allowed and hashed, so
+    // that a rejected password or a busy server doesn't cost the user their
+    // link.
+    if take_reset_token(&state.redis, &body.token).await? != Some(user_id) {
+        return Err(invalid_token());
+    }
+
+    let mut tx = state.database.begin().await?;
+
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2, password_salt = $3 WHERE id = $1",
+        user_id,
+        password_hash,
+        password_salt
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let revoked = revoke_user(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    revoked.deny(&state.redis).await?;
+    Session::destroy_all(&state.redis, user_id).await?;
+    discard_reset_tokens(&state.redis, user_id).await?;
+
+    Ok(())
 }
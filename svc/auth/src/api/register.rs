@@ -1,19 +1,26 @@
-use crate::state::AppState;
+use crate::{mail::preferred_locales, state::AppState};
+
+use super::{email_verify::send_verification, pwd_error};
 
 use lerpz_utils::{
     axum::{
         error::{HandlerError, HandlerResult},
         middelware::validate::Validated,
     },
-    pwd::hash_pwd,
+    pwd::{hash_pwd, policy::validate_pwd_policy_for},
 };
 
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
 use serde::Deserialize;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Deserialize, Debug, Validate)]
+#[validate(schema(function = "validate_password"))]
 pub struct RegisterRequest {
     #[validate(email(message = "Invalid email format"))]
     email: String,
@@ -31,29 +38,41 @@ pub struct RegisterRequest {
     password: String,
 }
 
+/// Checks the password against the password policy, which needs the username
+/// and email as well.
+fn validate_password(req: &RegisterRequest) -> Result<(), ValidationError> {
+    validate_pwd_policy_for("password", &req.password, &[&req.username, &req.email])
+}
+
 #[axum::debug_handler]
 pub async fn handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Validated(Json(body)): Validated<Json<RegisterRequest>>,
 ) -> HandlerResult<()> {
-    let mut db = state.database.acquire().await?;
-
     let password_salt = Uuid::new_v4().to_string();
-    let password_hash = hash_pwd(body.password, &password_salt).await?;
+    let password_hash = hash_pwd(body.password, &password_salt)
+        .await
+        .map_err(pwd_error)?;
+
+    // Only taken once the password is hashed, so that requests waiting to be
+    // hashed don't hold on to connections.
+    let mut db = state.database.acquire().await?;
 
-    sqlx::query!(
+    let user_id = sqlx::query_scalar!(
         "INSERT INTO users (
         username,
         primary_email,
         password_hash,
         password_salt
-        ) VALUES ($1, $2, $3, $4)",
+        ) VALUES ($1, $2, $3, $4)
+        RETURNING id",
         body.username,
         body.email,
         password_hash,
         password_salt
     )
-    .execute(&mut *db)
+    .fetch_one(&mut *db)
     .await
     .map_err(|err| match err {
         sqlx::Error::Database(db_err) => match db_err.kind() {
@@ -67,5 +86,11 @@ pub async fn handler(
         _ => HandlerError::from(err),
     })?;
 
+    // The user can ask for a new link if this one never arrives.
+    let locales = preferred_locales(&headers);
+    if let Err(err) = send_verification(&state, user_id, &body.email, &locales).await {
+        tracing::error!(error = %err, "failed sending verification email");
+    }
+
     Ok(())
 }
@@ -0,0 +1,125 @@
+//! Server-side login sessions.
+//!
+//! The session itself is stored in Redis, while the browser only holds an
+//! opaque session id in a cookie. The ids of the sessions of each user are
+//! kept in a set as well, so that all of them can be ended at once.
+
+use crate::config::CONFIG;
+
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The name of the cookie holding the session id.
+pub const SESSION_COOKIE: &str = "lerpz_session";
+
+/// How long a session lasts, in seconds.
+///
+/// The cookie itself expires when the browser is closed.
+const SESSION_TTL: u64 = 60 * 60 * 24 * 7;
+
+/// A user that has logged in.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub user_id: Uuid,
+    /// When the user logged in, as a unix timestamp.
+    pub auth_time: i64,
+}
+
+impl Session {
+    /// Starts a new session for the user and adds its cookie to `jar`.
+    pub async fn create(
+        redis: &redis::Client,
+        jar: CookieJar,
+        user_id: Uuid,
+    ) -> anyhow::Result<CookieJar> {
+        let id = super::oauth::generate_secret();
+        let session = Session {
+            user_id,
+            auth_time: Utc::now().timestamp(),
+        };
+
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let value = serde_json::to_string(&session)?;
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(session_key(&id), value, SESSION_TTL)
+            .sadd(user_sessions_key(user_id), &id)
+            .expire(user_sessions_key(user_id), SESSION_TTL as i64)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(jar.add(session_cookie(id)))
+    }
+
+    /// Finds the session the cookie in `jar` belongs to.
+    pub async fn find(redis: &redis::Client, jar: &CookieJar) -> anyhow::Result<Option<Self>> {
+        let Some(cookie) = jar.get(SESSION_COOKIE) else {
+            return Ok(None);
+        };
+
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let session: Option<String> = conn.get(session_key(cookie.value())).await?;
+
+        Ok(session.map(|s| serde_json::from_str(&s)).transpose()?)
+    }
+
+    /// Ends the session the cookie in `jar` belongs to and removes the cookie.
+    pub async fn destroy(redis: &redis::Client, jar: CookieJar) -> anyhow::Result<CookieJar> {
+        if let Some(cookie) = jar.get(SESSION_COOKIE) {
+            let mut conn = redis.get_multiplexed_async_connection().await?;
+            let session: Option<String> = conn.get_del(session_key(cookie.value())).await?;
+            if let Some(session) = session {
+                let session: Session = serde_json::from_str(&session)?;
+                let _: () = conn
+                    .srem(user_sessions_key(session.user_id), cookie.value())
+                    .await?;
+            }
+        }
+
+        Ok(jar.remove(Cookie::from(SESSION_COOKIE)))
+    }
+
+    /// Ends every session of the user.
+    pub async fn destroy_all(redis: &redis::Client, user_id: Uuid) -> anyhow::Result<()> {
+        let mut conn = redis.get_multiplexed_async_connection().await?;
+        let ids: Vec<String> = conn.smembers(user_sessions_key(user_id)).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for id in &ids {
+            pipe.del(session_key(id)).ignore();
+        }
+        pipe.del(user_sessions_key(user_id)).ignore();
+        let _: () = pipe.query_async(&mut conn).await?;
+
+        Ok(())
+    }
+}
+
+/// Builds the cookie holding the session id.
+///
+/// The cookie is sent on top-level navigations from other sites, since the
+/// authorization endpoint is reached through redirects from clients.
+fn session_cookie(id: String) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, id))
+        .path("/")
+        .http_only(true)
+        .secure(CONFIG.ENV != "dev")
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// The key a session is stored under in Redis.
+#[inline]
+fn session_key(id: &str) -> String {
+    format!("session:{id}")
+}
+
+/// The key the ids of the sessions of a user are stored under in Redis.
+#[inline]
+fn user_sessions_key(user_id: Uuid) -> String {
+    format!("session:user:{user_id}")
+}
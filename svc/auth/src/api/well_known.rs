@@ -0,0 +1,92 @@
+//! Discovery documents served under `/.well-known`.
+//!
+//! Source: https://openid.net/specs/openid-connect-discovery-1_0.html
+
+use crate::config::CONFIG;
+
+use super::oauth::keys::KEYS;
+
+use axum::{Json, routing::get};
+use lerpz_utils::jwt::{Algorithm, JwkSet};
+use serde::Serialize;
+
+pub fn router() -> axum::Router {
+    axum::Router::new()
+        .route("/openid-configuration", get(openid_configuration))
+        .route("/jwks.json", get(jwks))
+}
+
+/// The OpenID Provider metadata.
+///
+/// Source: https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenIdConfiguration {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+    revocation_endpoint: String,
+    jwks_uri: String,
+    response_types_supported: &'static [&'static str],
+    response_modes_supported: &'static [&'static str],
+    subject_types_supported: &'static [&'static str],
+    id_token_signing_alg_values_supported: Vec<Algorithm>,
+    scopes_supported: &'static [&'static str],
+    token_endpoint_auth_methods_supported: &'static [&'static str],
+    grant_types_supported: &'static [&'static str],
+    code_challenge_methods_supported: &'static [&'static str],
+    claims_supported: &'static [&'static str],
+}
+
+async fn openid_configuration() -> Json<OpenIdConfiguration> {
+    let issuer = CONFIG.ISSUER.trim_end_matches('/');
+    let endpoint = |path: &str| format!("{issuer}/api/oauth/{path}");
+
+    Json(OpenIdConfiguration {
+        issuer: issuer.into(),
+        authorization_endpoint: endpoint("authorize"),
+        token_endpoint: endpoint("token"),
+        userinfo_endpoint: endpoint("userinfo"),
+        revocation_endpoint: endpoint("revoke"),
+        jwks_uri: format!("{issuer}/.well-known/jwks.json"),
+        response_types_supported: &["code", "token", "id_token", "code id_token"],
+        response_modes_supported: &["query", "fragment", "form_post"],
+        subject_types_supported: &["public"],
+        id_token_signing_alg_values_supported: vec![KEYS.current().alg()],
+        scopes_supported: &["openid", "profile", "email"],
+        token_endpoint_auth_methods_supported: &[
+            "client_secret_basic",
+            "client_secret_post",
+            "none",
+        ],
+        grant_types_supported: &[
+            "authorization_code",
+            "client_credentials",
+            "password",
+            "refresh_token",
+        ],
+        code_challenge_methods_supported: &["plain", "S256"],
+        claims_supported: &[
+            "iss",
+            "sub",
+            "aud",
+            "exp",
+            "iat",
+            "auth_time",
+            "nonce",
+            "at_hash",
+            "c_hash",
+            "preferred_username",
+            "picture",
+            "email",
+            "email_verified",
+        ],
+    })
+}
+
+/// The public keys tokens issued by this server can be verified with.
+///
+/// Source: https://datatracker.ietf.org/doc/html/rfc7517#section-5
+async fn jwks() -> Json<JwkSet> {
+    Json(KEYS.jwks())
+}
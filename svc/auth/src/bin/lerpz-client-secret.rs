@@ -0,0 +1,51 @@
+//! Issues a new secret for a confidential OAuth client.
+//!
+//! The secret is hashed the same way as user passwords and stored on the
+//! client, replacing any previous secret. The plaintext secret is printed once
+//! and can't be recovered afterwards.
+//!
+//! Usage: `lerpz-client-secret <client_id>`, with `DATABASE_URL` set in the
+//! environment or in the `.env` file of the service.
+
+use lerpz_utils::{env::get_env, pwd::hash_pwd};
+use rand::{Rng, distr::Alphanumeric};
+use uuid::Uuid;
+
+/// Length of generated secrets, the same as other opaque secrets.
+const SECRET_LEN: usize = 43;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let _ = dotenvy::from_path(concat!(env!("CARGO_MANIFEST_DIR"), "/.env"));
+
+    let client_id = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("usage: lerpz-client-secret <client_id>"))?;
+    let client_id = Uuid::parse_str(&client_id)?;
+
+    let secret: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LEN)
+        .map(char::from)
+        .collect();
+    let secret_salt = Uuid::new_v4().to_string();
+    let secret_hash = hash_pwd(secret.as_str(), &secret_salt).await?;
+
+    let database = sqlx::PgPool::connect(&get_env("DATABASE_URL")?).await?;
+    let updated = sqlx::query!(
+        "UPDATE oauth_clients SET secret_hash = $2, secret_salt = $3 WHERE id = $1",
+        client_id,
+        secret_hash,
+        secret_salt
+    )
+    .execute(&database)
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        anyhow::bail!("no client with id {client_id}");
+    }
+
+    println!("{secret}");
+    Ok(())
+}
@@ -1,11 +1,12 @@
 //! Configuration module for the server.
 
-use std::{net::SocketAddr, sync::LazyLock};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, sync::LazyLock};
 
 use lerpz_utils::{
     env::{get_env, get_env_parse},
     generate_config,
 };
+use url::Url;
 
 /// The main configuration struct for the server.
 ///
@@ -17,5 +18,33 @@ generate_config!(
     ENV: String = get_env,
     ADDR: SocketAddr = get_env_parse,
     DATABASE_URL: String = get_env,
-    REDIS_URL: String = get_env
+    REDIS_URL: String = get_env,
+    ISSUER: String = get_env,
+    FRONTEND_URL: Url = get_env_parse,
+    JWT_KEYS_DIR: PathBuf = get_env_parse,
+    JWT_KEY_ID: String = get_env,
+    EMAIL_VERIFICATION: EmailVerification = get_env_parse,
+    MAIL_URL: String = get_env,
+    MAIL_FROM: String = get_env
 );
+
+/// Whether users have to verify their email before they can get tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailVerification {
+    /// Users can login and get tokens before verifying their email.
+    Optional,
+    /// Users can't login or get tokens until they have verified their email.
+    Required,
+}
+
+impl FromStr for EmailVerification {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "optional" => Ok(Self::Optional),
+            "required" => Ok(Self::Required),
+            _ => Err(format!("unknown email verification policy: {s}")),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Templates for the mail sent to users.
+//!
+//! The templates are embedded from the `templates` folder, with a folder for
+//! each locale.
+
+use std::sync::LazyLock;
+
+use axum::http::{HeaderMap, header};
+use lerpz_utils::mail::{Template, Templates, template::accept_language};
+
+/// Every template, in every supported locale.
+pub static TEMPLATES: LazyLock<Templates> = LazyLock::new(|| {
+    macro_rules! template {
+        ($locale:literal, $name:literal, $subject:literal) => {
+            Template::new(
+                $subject,
+                include_str!(concat!("../templates/", $locale, "/", $name, ".txt")),
+            )
+            .with_html(include_str!(concat!(
+                "../templates/",
+                $locale,
+                "/",
+                $name,
+                ".html"
+            )))
+        };
+    }
+
+    Templates::new("en")
+        .with(
+            "verify_email",
+            "en",
+            template!("en", "verify_email", "Verify your email"),
+        )
+        .with(
+            "reset_password",
+            "en",
+            template!("en", "reset_password", "Reset your password"),
+        )
+        .with(
+            "verify_email",
+            "da",
+            template!("da", "verify_email", "Bekræft din email"),
+        )
+        .with(
+            "reset_password",
+            "da",
+            template!("da", "reset_password", "Nulstil din adgangskode"),
+        )
+});
+
+/// The locales the client prefers, most preferred first.
+pub fn preferred_locales(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(accept_language)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates() {
+        for name in ["verify_email", "reset_password"] {
+            for locale in ["en", "da"] {
+                let mail = TEMPLATES
+                    .render(
+                        name,
+                        &[locale],
+                        "a@example.com",
+                        &[("link", "https://x/?a&b")],
+                    )
+                    .unwrap();
+                assert!(mail.text.contains("https://x/?a&b"));
+                assert!(mail.html.unwrap().contains("https://x/?a&amp;b"));
+            }
+        }
+    }
+}
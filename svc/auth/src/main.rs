@@ -1,12 +1,16 @@
 mod api;
 mod config;
+mod mail;
 mod state;
 
 use crate::config::CONFIG;
 use crate::state::AppState;
 
 use axum::Router;
-use lerpz_utils::axum::shutdown_signal;
+use lerpz_utils::{
+    axum::shutdown_signal,
+    mail::{MailQueue, MailWorker},
+};
 
 use std::{sync::Arc, time::Duration};
 
@@ -31,6 +35,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // The password scheme and policy are configured from the environment, so
+    // load them up front instead of failing on the first login or signup.
+    lerpz_utils::pwd::scheme::init()
+        .unwrap_or_else(|err| panic!("can't load password scheme: {err}"));
+    lerpz_utils::pwd::policy::policy();
+    lerpz_utils::pwd::pool::pool();
+
     let database_pool = sqlx::postgres::PgPoolOptions::new()
         .max_connections(5)
         .acquire_timeout(Duration::from_secs(3))
@@ -43,12 +54,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .unwrap_or_else(|err| panic!("can't connect to redis: {err}")),
     );
 
+    // Mail is sent by a background worker, so that a slow mail server never
+    // holds up a request.
+    let transport = lerpz_utils::mail::transport(&CONFIG.MAIL_URL, &CONFIG.MAIL_FROM)
+        .unwrap_or_else(|err| panic!("can't create mail transport: {err}"));
+    let mail_queue = MailQueue::new(redis_pool.clone(), "mail:queue");
+    tokio::spawn(MailWorker::new(mail_queue.clone(), transport).run());
+
     let state = AppState {
         database: database_pool,
+        bearer: crate::api::bearer_auth(redis_pool.clone()),
         redis: redis_pool,
+        mailer: Arc::new(mail_queue),
     };
 
-    let app = Router::new().nest("/api", crate::api::router(state));
+    let app = Router::new()
+        .nest("/api", crate::api::router(state))
+        .nest("/.well-known", crate::api::well_known::router());
 
     let listener = tokio::net::TcpListener::bind(&CONFIG.ADDR).await?;
     tracing::info!("server started listening on {}", CONFIG.ADDR);
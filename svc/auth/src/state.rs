@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
 use axum::extract::FromRef;
+use lerpz_utils::{axum::middelware::bearer::BearerAuth, mail::Mailer};
 use sqlx::{Pool, Postgres};
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: sqlx::PgPool,
     pub redis: Arc<redis::Client>,
+    pub bearer: BearerAuth,
+    pub mailer: Arc<dyn Mailer>,
 }
 
 impl FromRef<AppState> for Pool<Postgres> {
@@ -20,3 +23,9 @@ impl FromRef<AppState> for Arc<redis::Client> {
         state.redis.clone() 
     }
 }
+
+impl FromRef<AppState> for BearerAuth {
+    fn from_ref(state: &AppState) -> Self {
+        state.bearer.clone()
+    }
+}